@@ -60,3 +60,38 @@ fn bench_samples_set_tag(b: &mut Bencher) {
         });
     }
 }
+
+/// Benchmark performance of `&str`s being parsed into `MessageRef`s.
+#[bench]
+fn bench_ref_samples_0tags(b: &mut Bencher) {
+    for init in SAMPLES {
+        b.iter(|| {
+            let _msg: MessageRef = MessageRef::parse(init).expect("Parse Failed");
+        });
+    }
+}
+
+/// Benchmark performance of `&str`s being parsed into `MessageRef`s and having
+///     IRC Tag values extracted.
+#[bench]
+fn bench_ref_samples_3tags(b: &mut Bencher) {
+    for init in SAMPLES {
+        b.iter(|| {
+            let msg: MessageRef = MessageRef::parse(init).expect("Parse Failed");
+            msg.get_tag("bits");
+            msg.get_tag("badges");
+            msg.get_tag("badge-info");
+        });
+    }
+}
+
+/// Benchmark performance of `&str`s being parsed into `MessageRef`s and then
+///     converted into owned `Message`s.
+#[bench]
+fn bench_ref_samples_to_owned(b: &mut Bencher) {
+    for init in SAMPLES {
+        b.iter(|| {
+            let _msg: Message = MessageRef::parse(init).expect("Parse Failed").to_message();
+        });
+    }
+}
@@ -0,0 +1,60 @@
+//! Offline replay of raw Twitch IRC lines, such as those written by the
+//!     `HTCAPTURE` command.
+//!
+//! Each line is run through the plugin exactly as if HexChat had received it,
+//!     with a [`MockHost`] standing in for HexChat. Everything the plugin would
+//!     have printed, and every command it would have run, is written to stdout.
+//!     Lines that HexChat would have printed by itself are not shown.
+//!
+//! Usage: `hextwitch-replay [<file>]`, reading from stdin if no file is given.
+//!     Requires the `mock` feature, as in `cargo run --features mock --bin
+//!     hextwitch-replay`.
+
+use std::{
+    env,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    process::ExitCode,
+};
+use hextwitch::host::MockHost;
+
+
+fn main() -> ExitCode {
+    let input: Box<dyn BufRead> = match env::args().nth(1) {
+        Some(path) if path != "-" => match File::open(&path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(e) => {
+                eprintln!("Failed to open {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        _ => Box::new(BufReader::new(io::stdin())),
+    };
+
+    let host = MockHost::new("#replay").install();
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    for (num, line) in input.lines().enumerate() {
+        let line: String = match line {
+            Ok(line) => line,
+            Err(e) => {
+                eprintln!("Failed to read line {}: {}", num + 1, e);
+                return ExitCode::FAILURE;
+            }
+        };
+
+        if line.trim().is_empty() { continue; }
+
+        host.replay(&line);
+
+        for output in host.take_lines() {
+            if writeln!(out, "{}", output).is_err() {
+                //  Most likely, stdout was closed by a pipe.
+                return ExitCode::SUCCESS;
+            }
+        }
+    }
+
+    ExitCode::SUCCESS
+}
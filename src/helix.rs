@@ -0,0 +1,518 @@
+//! Client for the Twitch Helix API.
+//!
+//! Twitch has removed the IRC commands for moderating and editing a Channel,
+//!     and these are now only available through its HTTP API. This requires an
+//!     OAuth token, and the Client ID of the application the token was issued
+//!     to. Both are kept in plugin preferences, along with the token used to
+//!     refresh the OAuth token when it expires.
+//!
+//! So far, only the client itself is here. The moderation commands and the
+//!     Channel menus still send the old chat commands, and are yet to be moved
+//!     over to it.
+//!
+//! The URLs of the API are configurable, so that a [`Client`] may be pointed
+//!     at a local server in tests.
+
+//  Saving Credentials returns `Result<_, ()>`, as preferences do.
+#![allow(clippy::result_unit_err)]
+
+use std::{
+    error::Error,
+    fmt::{Display, Formatter},
+    time::Duration,
+};
+use serde_json::{json, Value};
+use crate::prefs::*;
+
+
+/// Base URL of the Helix API.
+pub const API_URL: &str = "https://api.twitch.tv/helix";
+/// Base URL of the Twitch authentication server.
+pub const AUTH_URL: &str = "https://id.twitch.tv/oauth2";
+
+/// How long to wait for a response before giving up. Requests are made on a
+///     worker thread, but the result is only reported once they finish, so
+///     this should still be short.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+
+/// A reason that a request to the API failed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum HelixError {
+    /// No Client ID or OAuth token has been set.
+    NotConfigured,
+    /// The request was rejected as malformed.
+    BadRequest(String),
+    /// The OAuth token is invalid or has expired, and could not be refreshed.
+    Unauthorized(String),
+    /// The token is valid, but does not allow this request. Usually this means
+    ///     that it lacks a scope, or that the User is not a moderator.
+    Forbidden(String),
+    /// The User or other resource does not exist.
+    NotFound(String),
+    /// Too many requests have been made. Contains the Unix time at which the
+    ///     limit will be reset, if the server gave one.
+    RateLimited(Option<u64>),
+    /// Any other error status.
+    Status(u16, String),
+    /// The server could not be reached.
+    Transport(String),
+    /// The server responded with something other than the expected JSON.
+    BadResponse,
+}
+
+impl HelixError {
+    /// Interpret an error status from the API. Twitch normally gives a JSON
+    ///     body with a `message`, but the raw body is used if there is not one.
+    fn from_status(status: u16, body: &str, reset: Option<u64>) -> Self {
+        let message: String = serde_json::from_str::<Value>(body).ok()
+            .and_then(|value| value["message"].as_str().map(String::from))
+            .unwrap_or_else(|| body.trim().to_owned());
+
+        match status {
+            400 => Self::BadRequest(message),
+            401 => Self::Unauthorized(message),
+            403 => Self::Forbidden(message),
+            404 => Self::NotFound(message),
+            429 => Self::RateLimited(reset),
+            _ => Self::Status(status, message),
+        }
+    }
+}
+
+impl Display for HelixError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotConfigured => f.write_str("no Client ID or OAuth token has been set"),
+            Self::BadRequest(msg) => write!(f, "bad request: {}", msg),
+            Self::Unauthorized(msg) => write!(f, "not authorized: {}", msg),
+            Self::Forbidden(msg) => write!(f, "forbidden: {}", msg),
+            Self::NotFound(msg) => write!(f, "not found: {}", msg),
+            Self::RateLimited(_) => f.write_str("rate limit reached"),
+            Self::Status(status, msg) => write!(f, "error {}: {}", status, msg),
+            Self::Transport(msg) => write!(f, "request failed: {}", msg),
+            Self::BadResponse => f.write_str("response could not be read"),
+        }
+    }
+}
+
+impl Error for HelixError {}
+
+
+/// The values needed to make requests to the API.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Credentials {
+    pub client_id: String,
+    /// OAuth token, without any `oauth:` prefix.
+    pub token: String,
+    /// Token used to get a new OAuth token when the current one expires. Not
+    ///     every token can be refreshed.
+    pub refresh: Option<String>,
+}
+
+impl Credentials {
+    pub fn new(client_id: &str, token: &str, refresh: Option<&str>) -> Self {
+        Self {
+            client_id: client_id.to_owned(),
+            token: token.trim_start_matches("oauth:").to_owned(),
+            refresh: refresh.map(String::from),
+        }
+    }
+
+    /// Read the Credentials from preferences. Returns `None` if either the
+    ///     Client ID or the OAuth token is not set.
+    ///
+    /// Return: `Option<Credentials>`
+    pub fn load() -> Option<Self> {
+        Some(Self {
+            client_id: PREF_HELIX_CLIENT_ID.get().filter(|s| !s.is_empty())?,
+            token: PREF_HELIX_TOKEN.get().filter(|s| !s.is_empty())?,
+            refresh: PREF_HELIX_REFRESH.get().filter(|s| !s.is_empty()),
+        })
+    }
+
+    /// Write the Credentials to preferences.
+    pub fn save(&self) -> Result<(), ()> {
+        PREF_HELIX_CLIENT_ID.set(&self.client_id)?;
+        PREF_HELIX_TOKEN.set(&self.token)?;
+
+        match &self.refresh {
+            Some(refresh) => PREF_HELIX_REFRESH.set(refresh),
+            None => PREF_HELIX_REFRESH.unset(),
+        }
+    }
+
+    /// Remove any Credentials from preferences.
+    pub fn clear() -> Result<(), ()> {
+        PREF_HELIX_CLIENT_ID.unset()?;
+        PREF_HELIX_TOKEN.unset()?;
+        PREF_HELIX_REFRESH.unset()
+    }
+}
+
+
+/// Details of an OAuth token, as reported by the authentication server.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TokenInfo {
+    pub client_id: String,
+    /// Login name of the User. Not present for app tokens.
+    pub login: Option<String>,
+    pub user_id: Option<String>,
+    pub scopes: Vec<String>,
+    /// Seconds until the token expires.
+    pub expires_in: Option<u64>,
+}
+
+
+/// A User, as returned by the API.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HelixUser {
+    pub id: String,
+    pub login: String,
+    pub display_name: String,
+}
+
+
+/// A connection to the API, with a set of [`Credentials`].
+pub struct Client {
+    agent: ureq::Agent,
+    api_url: String,
+    auth_url: String,
+    credentials: Credentials,
+    /// Whether the Credentials have been changed by a refresh since this Client
+    ///     was created.
+    refreshed: bool,
+}
+
+impl Client {
+    pub fn new(credentials: Credentials) -> Self {
+        Self::with_urls(credentials, API_URL, AUTH_URL)
+    }
+
+    /// Create a Client that sends requests to somewhere other than Twitch.
+    pub fn with_urls(
+        credentials: Credentials,
+        api_url: impl Into<String>,
+        auth_url: impl Into<String>,
+    ) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(TIMEOUT).build(),
+            api_url: api_url.into(),
+            auth_url: auth_url.into(),
+            credentials,
+            refreshed: false,
+        }
+    }
+
+    /// Create a Client with the Credentials stored in preferences.
+    ///
+    /// Return: `Result<Client, HelixError>`
+    pub fn from_prefs() -> Result<Self, HelixError> {
+        Credentials::load().map(Self::new).ok_or(HelixError::NotConfigured)
+    }
+
+    pub fn credentials(&self) -> &Credentials { &self.credentials }
+
+    /// Check whether the OAuth token has been replaced by a refresh. If it has,
+    ///     the new [`Credentials`] should be saved.
+    pub fn was_refreshed(&self) -> bool { self.refreshed }
+
+    /// Ask the authentication server about the OAuth token. This does not try
+    ///     to refresh the token if it is invalid.
+    ///
+    /// Return: `Result<TokenInfo, HelixError>`
+    pub fn validate(&self) -> Result<TokenInfo, HelixError> {
+        let value: Value = read(
+            self.agent.get(&format!("{}/validate", self.auth_url))
+                .set("Authorization", &format!("OAuth {}", self.credentials.token))
+                .call(),
+        )?;
+
+        Ok(TokenInfo {
+            client_id: value["client_id"].as_str().ok_or(HelixError::BadResponse)?.to_owned(),
+            login: value["login"].as_str().map(String::from),
+            user_id: value["user_id"].as_str().map(String::from),
+            scopes: value["scopes"].as_array()
+                .map(|scopes| scopes.iter().filter_map(Value::as_str).map(String::from).collect())
+                .unwrap_or_default(),
+            expires_in: value["expires_in"].as_u64(),
+        })
+    }
+
+    /// Exchange the refresh token for a new OAuth token.
+    ///
+    /// No client secret is sent, so this only works for a token that was
+    ///     issued to a public client. Twitch refuses to refresh one that was
+    ///     issued to a confidential client.
+    pub fn refresh(&mut self) -> Result<(), HelixError> {
+        let refresh: String = self.credentials.refresh.clone().ok_or_else(
+            || HelixError::Unauthorized(String::from("token cannot be refreshed")),
+        )?;
+
+        let value: Value = read(
+            self.agent.post(&format!("{}/token", self.auth_url)).send_form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh),
+                ("client_id", &self.credentials.client_id),
+            ]),
+        )?;
+
+        self.credentials.token = value["access_token"].as_str()
+            .ok_or(HelixError::BadResponse)?
+            .to_owned();
+
+        if let Some(refresh) = value["refresh_token"].as_str() {
+            self.credentials.refresh = Some(refresh.to_owned());
+        }
+
+        self.refreshed = true;
+        Ok(())
+    }
+
+    /// Make a request to the API, and return the JSON body of the response. If
+    ///     the token is rejected, it is refreshed, if possible, and the request
+    ///     is tried once more.
+    ///
+    /// Input: `&str`, `&str`, `&[(&str, &str)]`, `Option<&Value>`
+    /// Return: `Result<Value, HelixError>`
+    pub fn request(
+        &mut self,
+        method: &str,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Value, HelixError> {
+        match self.request_once(method, path, query, body) {
+            Err(HelixError::Unauthorized(_)) if self.credentials.refresh.is_some() => {
+                self.refresh()?;
+                self.request_once(method, path, query, body)
+            }
+            result => result,
+        }
+    }
+
+    fn request_once(
+        &self,
+        method: &str,
+        path: &str,
+        query: &[(&str, &str)],
+        body: Option<&Value>,
+    ) -> Result<Value, HelixError> {
+        let mut req = self.agent.request(method, &format!("{}{}", self.api_url, path))
+            .set("Authorization", &format!("Bearer {}", self.credentials.token))
+            .set("Client-Id", &self.credentials.client_id);
+
+        for (key, value) in query {
+            req = req.query(key, value);
+        }
+
+        read(match body {
+            Some(body) => req.set("Content-Type", "application/json")
+                .send_string(&body.to_string()),
+            None => req.call(),
+        })
+    }
+
+    /// Look up a User by login name.
+    ///
+    /// Input: `&str`
+    /// Return: `Result<HelixUser, HelixError>`
+    pub fn get_user(&mut self, login: &str) -> Result<HelixUser, HelixError> {
+        let value: Value = self.request("GET", "/users", &[("login", login)], None)?;
+        let user: &Value = value["data"].get(0)
+            .ok_or_else(|| HelixError::NotFound(format!("no User named {:?}", login)))?;
+        let field = |key: &str| user[key].as_str()
+            .map(String::from)
+            .ok_or(HelixError::BadResponse);
+
+        Ok(HelixUser {
+            id: field("id")?,
+            login: field("login")?,
+            display_name: field("display_name")?,
+        })
+    }
+
+    /// Ban a User from a Channel, or time them out if a duration is given.
+    ///
+    /// Input: `&str`, `&str`, `&str`, `Option<u32>`, `&str`
+    pub fn ban_user(
+        &mut self,
+        broadcaster_id: &str,
+        moderator_id: &str,
+        user_id: &str,
+        duration: Option<u32>,
+        reason: &str,
+    ) -> Result<(), HelixError> {
+        let mut data: Value = json!({ "user_id": user_id, "reason": reason });
+
+        if let Some(duration) = duration {
+            data["duration"] = duration.into();
+        }
+
+        self.request(
+            "POST",
+            "/moderation/bans",
+            &[("broadcaster_id", broadcaster_id), ("moderator_id", moderator_id)],
+            Some(&json!({ "data": data })),
+        ).map(drop)
+    }
+}
+
+
+/// Read the JSON body of a response, or interpret its error. An empty body, as
+///     given by some successful requests, is read as `null`.
+fn read(result: Result<ureq::Response, ureq::Error>) -> Result<Value, HelixError> {
+    match result {
+        Ok(resp) => {
+            let text: String = resp.into_string()
+                .map_err(|err| HelixError::Transport(err.to_string()))?;
+
+            if text.trim().is_empty() {
+                Ok(Value::Null)
+            } else {
+                serde_json::from_str(&text).map_err(|_| HelixError::BadResponse)
+            }
+        }
+        Err(ureq::Error::Status(status, resp)) => {
+            let reset: Option<u64> = resp.header("Ratelimit-Reset")
+                .and_then(|s| s.parse().ok());
+            let text: String = resp.into_string().unwrap_or_default();
+
+            Err(HelixError::from_status(status, &text, reset))
+        }
+        Err(ureq::Error::Transport(err)) => Err(HelixError::Transport(err.to_string())),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+    };
+    use super::*;
+
+    /// Serve canned responses, one connection at a time, from a local port.
+    ///     Each request line received is sent back through the channel.
+    fn mock_server(responses: Vec<(u16, &'static str)>) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url: String = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            for (status, body) in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                let mut length: usize = 0;
+
+                reader.read_line(&mut request).unwrap();
+
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+
+                    if line.trim().is_empty() { break; }
+
+                    if let Some((key, value)) = line.split_once(':') {
+                        if key.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+
+                let mut content = vec![0; length];
+                reader.read_exact(&mut content).unwrap();
+                tx.send(format!("{} {}", request.trim(), String::from_utf8_lossy(&content)).trim().to_owned()).unwrap();
+
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 {} X\r\nContent-Length: {}\r\nRatelimit-Reset: 1700000000\r\nConnection: close\r\n\r\n{}",
+                    status, body.len(), body,
+                ).unwrap();
+            }
+        });
+
+        (url, rx)
+    }
+
+    fn client(url: &str, refresh: Option<&str>) -> Client {
+        Client::with_urls(Credentials::new("cid", "oauth:old", refresh), url, url)
+    }
+
+    #[test]
+    fn test_validate() {
+        let (url, rx) = mock_server(vec![
+            (200, r#"{"client_id":"cid","login":"asdf","scopes":["moderator:manage:banned_users"],"user_id":"1337","expires_in":3600}"#),
+            (401, r#"{"status":401,"message":"invalid access token"}"#),
+        ]);
+        let client = client(&url, None);
+
+        assert_eq!(client.validate(), Ok(TokenInfo {
+            client_id: String::from("cid"),
+            login: Some(String::from("asdf")),
+            user_id: Some(String::from("1337")),
+            scopes: vec![String::from("moderator:manage:banned_users")],
+            expires_in: Some(3600),
+        }));
+        assert_eq!(rx.recv().unwrap(), "GET /validate HTTP/1.1");
+
+        assert_eq!(
+            client.validate(),
+            Err(HelixError::Unauthorized(String::from("invalid access token"))),
+        );
+    }
+
+    #[test]
+    fn test_refresh() {
+        let (url, rx) = mock_server(vec![
+            (401, r#"{"error":"Unauthorized","status":401,"message":"Invalid OAuth token"}"#),
+            (200, r#"{"access_token":"new","refresh_token":"next","expires_in":14400}"#),
+            (200, r#"{"data":[{"id":"42","login":"qwert","display_name":"Qwert"}]}"#),
+        ]);
+        let mut client = client(&url, Some("first"));
+
+        assert_eq!(client.get_user("qwert"), Ok(HelixUser {
+            id: String::from("42"),
+            login: String::from("qwert"),
+            display_name: String::from("Qwert"),
+        }));
+        assert!(client.was_refreshed());
+        assert_eq!(client.credentials(), &Credentials::new("cid", "new", Some("next")));
+
+        assert_eq!(rx.recv().unwrap(), "GET /users?login=qwert HTTP/1.1");
+        assert_eq!(
+            rx.recv().unwrap(),
+            "POST /token HTTP/1.1 grant_type=refresh_token&refresh_token=first&client_id=cid",
+        );
+        assert_eq!(rx.recv().unwrap(), "GET /users?login=qwert HTTP/1.1");
+    }
+
+    #[test]
+    fn test_errors() {
+        let (url, _rx) = mock_server(vec![
+            (403, r#"{"error":"Forbidden","status":403,"message":"The user is not a moderator"}"#),
+            (429, ""),
+            (200, r#"{"data":[]}"#),
+            (500, "oops"),
+        ]);
+        let mut client = client(&url, None);
+
+        assert_eq!(
+            client.ban_user("1", "2", "3", Some(600), "spam"),
+            Err(HelixError::Forbidden(String::from("The user is not a moderator"))),
+        );
+        assert_eq!(client.get_user("x"), Err(HelixError::RateLimited(Some(1700000000))));
+        assert!(matches!(client.get_user("x"), Err(HelixError::NotFound(_))));
+        assert_eq!(client.get_user("x"), Err(HelixError::Status(500, String::from("oops"))));
+        assert!(!client.was_refreshed());
+
+        assert!(matches!(
+            Client::with_urls(Credentials::default(), "http://127.0.0.1:1", "").get_user("x"),
+            Err(HelixError::Transport(_)),
+        ));
+    }
+}
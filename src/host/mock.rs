@@ -0,0 +1,300 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    fmt::{Display, Formatter},
+    path::PathBuf,
+    rc::Rc,
+};
+use chrono::Utc;
+use hexchat::{EatMode, PrintEvent};
+use crate::{
+    ht_core::{cb_notice, cb_print, cb_server},
+    irc::Message,
+    NETWORK,
+};
+use super::{Host, set_host};
+
+
+/// Name of a [`PrintEvent`], for display. Only the events used by the plugin
+///     are known by name.
+///
+/// Input: `PrintEvent`
+/// Return: `&str`
+pub fn event_name(event: PrintEvent) -> &'static str {
+    match event {
+        PrintEvent::CHANNEL_ACTION => "Channel Action",
+        PrintEvent::CHANNEL_ACTION_HILIGHT => "Channel Action Hilight",
+        PrintEvent::CHANNEL_DEOP => "Channel DeOp",
+        PrintEvent::CHANNEL_MESSAGE => "Channel Message",
+        PrintEvent::CHANNEL_MSG_HILIGHT => "Channel Msg Hilight",
+        PrintEvent::CHANNEL_OPERATOR => "Channel Operator",
+        PrintEvent::CHANNEL_URL => "Channel Url",
+        PrintEvent::JOIN => "Join",
+        PrintEvent::MESSAGE_SEND => "Message Send",
+        PrintEvent::MOTD => "Motd",
+        PrintEvent::NOTICE_SEND => "Notice Send",
+        PrintEvent::PRIVATE_ACTION => "Private Action",
+        PrintEvent::PRIVATE_ACTION_TO_DIALOG => "Private Action to Dialog",
+        PrintEvent::PRIVATE_MESSAGE => "Private Message",
+        PrintEvent::PRIVATE_MESSAGE_TO_DIALOG => "Private Message to Dialog",
+        PrintEvent::SERVER_ERROR => "Server Error",
+        PrintEvent::SERVER_NOTICE => "Server Notice",
+        PrintEvent::TOPIC => "Topic",
+        PrintEvent::WHOIS_AUTHENTICATED => "WhoIs Authenticated",
+        PrintEvent::WHOIS_SERVER_LINE => "WhoIs Server Line",
+        PrintEvent::YOUR_ACTION => "Your Action",
+        PrintEvent::YOUR_MESSAGE => "Your Message",
+        _ => "Unknown",
+    }
+}
+
+
+/// Something done by the plugin through a [`MockHost`].
+#[derive(Clone)]
+pub enum Output {
+    Plain(String),
+    /// A [`PrintEvent`], with the Channel it was printed to. The Channel is
+    ///     `None` if it was printed to the current Channel.
+    Event {
+        channel: Option<String>,
+        event: PrintEvent,
+        args: Vec<String>,
+    },
+    Command(String),
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Plain(text) => f.write_str(text),
+            Self::Event { channel, event, args } => {
+                if let Some(channel) = channel {
+                    write!(f, "{} ", channel)?;
+                }
+
+                write!(f, "[{}] {}", event_name(*event), args.join(" | "))
+            }
+            Self::Command(command) => write!(f, "/{}", command),
+        }
+    }
+}
+
+
+/// An imitation of HexChat, which records everything printed and every command
+///     sent, rather than doing anything with them. Preferences are kept only in
+///     memory.
+pub struct MockHost {
+    pub channel: RefCell<String>,
+    pub network: RefCell<Option<String>>,
+    pub nickname: RefCell<String>,
+    /// Whether the current Channel is the one being viewed.
+    pub focused: Cell<bool>,
+    /// Channels for which a tab is open.
+    pub tabs: RefCell<HashSet<String>>,
+    pub topics: RefCell<HashMap<String, String>>,
+    pub prefs: RefCell<HashMap<String, String>>,
+    pub config_dir: PathBuf,
+    output: RefCell<Vec<Output>>,
+}
+
+impl MockHost {
+    /// Create a new Host, on the Twitch network, in the given Channel, which is
+    ///     open but not focused.
+    pub fn new(channel: &str) -> Self {
+        Self {
+            channel: RefCell::new(channel.to_owned()),
+            network: RefCell::new(Some(NETWORK.to_owned())),
+            nickname: RefCell::new(String::from("hextwitch")),
+            focused: Cell::new(false),
+            tabs: RefCell::new(std::iter::once(channel.to_owned()).collect()),
+            topics: Default::default(),
+            prefs: Default::default(),
+            config_dir: std::env::temp_dir().join("hextwitch-mock"),
+            output: Default::default(),
+        }
+    }
+
+    /// Install this Host for the current thread, returning a handle to it. Any
+    ///     preferences not already set are given their initial values, as they
+    ///     would be when the plugin is loaded.
+    ///
+    /// Return: `Rc<MockHost>`
+    pub fn install(self) -> Rc<Self> {
+        let host: Rc<Self> = Rc::new(self);
+        set_host(host.clone());
+        crate::prefs::init_prefs();
+        host
+    }
+
+    /// Remove and return everything recorded so far.
+    ///
+    /// Return: `Vec<Output>`
+    pub fn take(&self) -> Vec<Output> {
+        self.output.take()
+    }
+
+    /// Remove and return everything recorded so far, as lines of text.
+    ///
+    /// Return: `Vec<String>`
+    pub fn take_lines(&self) -> Vec<String> {
+        self.take().iter().map(Output::to_string).collect()
+    }
+
+    /// Handle a raw line as HexChat would, including the print event that
+    ///     HexChat emits for a chat message or a NOTICE. The current Channel is
+    ///     moved to the Channel of the line first.
+    ///
+    /// Input: `&str`
+    pub fn replay(&self, raw: &str) {
+        let msg: Message = match raw.parse() {
+            Ok(msg) => msg,
+            Err(_) => {
+                cb_server(&[], Utc::now(), raw.to_owned());
+                return;
+            }
+        };
+
+        if let Some(channel) = msg.args.first().filter(|arg| arg.starts_with('#')) {
+            self.channel.replace(channel.clone());
+            self.tabs.borrow_mut().insert(channel.clone());
+        }
+
+        let eat: EatMode = cb_server(&[], Utc::now(), raw.to_owned());
+
+        if !matches!(eat, EatMode::None) { return; }
+
+        if msg.command == "NOTICE" {
+            cb_notice(&[msg.text().to_owned(), msg.author().to_owned()], Utc::now());
+        } else if msg.command == "PRIVMSG" {
+            let (etype, text) = match msg.text().strip_prefix("\x01ACTION ") {
+                Some(action) => (PrintEvent::CHANNEL_ACTION, action.trim_end_matches('\x01')),
+                None => (PrintEvent::CHANNEL_MESSAGE, msg.text()),
+            };
+
+            cb_print(etype, &[
+                msg.author().to_owned(),
+                text.to_owned(),
+                String::new(),
+                String::new(),
+            ], Utc::now());
+        }
+    }
+
+    fn record(&self, output: Output) {
+        self.output.borrow_mut().push(output);
+    }
+}
+
+impl Host for MockHost {
+    fn print_plain(&self, text: &str) {
+        self.record(Output::Plain(text.to_owned()));
+    }
+
+    fn print_event(&self, event: PrintEvent, args: &[&str]) {
+        self.record(Output::Event {
+            channel: None,
+            event,
+            args: args.iter().map(|&s| s.to_owned()).collect(),
+        });
+    }
+
+    fn print_event_to(&self, channel: &str, event: PrintEvent, args: &[&str]) -> bool {
+        if self.has_channel(channel) {
+            self.record(Output::Event {
+                channel: Some(channel.to_owned()),
+                event,
+                args: args.iter().map(|&s| s.to_owned()).collect(),
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    fn send_command(&self, command: &str) {
+        //  Commands that open tabs must really do so, because the plugin will
+        //      look for the tab immediately afterwards.
+        let mut words = command.split_ascii_whitespace();
+
+        match (words.next(), words.next()) {
+            (Some(cmd), Some(target))
+            if cmd.eq_ignore_ascii_case("QUERY") || cmd.eq_ignore_ascii_case("JOIN")
+            => { self.tabs.borrow_mut().insert(target.to_owned()); }
+            _ => {}
+        }
+
+        self.record(Output::Command(command.to_owned()));
+    }
+
+    fn strip_formatting(&self, text: &str) -> Result<String, ()> {
+        let mut out = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\x03' => {
+                    //  A color code may be followed by a foreground number and
+                    //      then a comma and a background number, each of up
+                    //      to two digits.
+                    for _ in 0..2 { chars.next_if(char::is_ascii_digit); }
+
+                    let mut ahead = chars.clone();
+                    if ahead.next() == Some(',')
+                        && ahead.peek().is_some_and(char::is_ascii_digit)
+                    {
+                        chars.next();
+                        for _ in 0..2 { chars.next_if(char::is_ascii_digit); }
+                    }
+                }
+                '\x02' | '\x0F' | '\x11' | '\x16' | '\x1D' | '\x1E' | '\x1F' => {}
+                c => out.push(c),
+            }
+        }
+
+        Ok(out)
+    }
+
+    fn get_channel_name(&self) -> String { self.channel.borrow().clone() }
+
+    fn get_network_name(&self) -> Option<String> { self.network.borrow().clone() }
+
+    fn has_channel(&self, channel: &str) -> bool {
+        self.tabs.borrow().contains(channel)
+    }
+
+    fn is_focused(&self) -> bool { self.focused.get() }
+
+    fn get_topic(&self, channel: &str) -> Option<String> {
+        self.topics.borrow().get(channel).cloned()
+    }
+
+    fn get_nickname(&self) -> String { self.nickname.borrow().clone() }
+
+    fn get_config_dir(&self) -> PathBuf { self.config_dir.clone() }
+
+    fn get_pref_int(&self, name: &str) -> Option<u32> {
+        self.prefs.borrow().get(name)?.parse().ok()
+    }
+
+    fn set_pref_int(&self, name: &str, value: u32) -> Result<(), ()> {
+        self.set_pref_string(name, &value.to_string())
+    }
+
+    fn get_pref_string(&self, name: &str) -> Option<String> {
+        self.prefs.borrow().get(name).cloned()
+    }
+
+    fn set_pref_string(&self, name: &str, value: &str) -> Result<(), ()> {
+        self.prefs.borrow_mut().insert(name.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    fn delete_pref(&self, name: &str) -> Result<(), ()> {
+        self.prefs.borrow_mut().remove(name);
+        Ok(())
+    }
+
+    fn get_prefs(&self) -> Vec<String> {
+        self.prefs.borrow().keys().cloned().collect()
+    }
+}
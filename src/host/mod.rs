@@ -0,0 +1,209 @@
+//! Interface between the plugin and the program running it.
+//!
+//! Everything the plugin needs from HexChat, other than the registration of
+//!     hooks, goes through the [`Host`] trait. Normally this is [`HexChat`],
+//!     which simply passes each call along, but a [`MockHost`] may be installed
+//!     instead, so that the plugin can be run where HexChat is not present,
+//!     such as in tests. The mock is only built for tests, or with the `mock`
+//!     feature.
+//!
+//! The free functions in this module mirror those of the `hexchat` crate, and
+//!     call the Host installed for the current thread.
+
+//  Preference functions return `Result<_, ()>` because those of the `hexchat`
+//      crate do, and there is no more detail to give.
+#![allow(clippy::result_unit_err)]
+
+#[cfg(any(test, feature = "mock"))]
+mod mock;
+
+use std::{cell::RefCell, path::PathBuf, rc::Rc};
+use hexchat::PrintEvent;
+use crate::NETWORK;
+#[cfg(any(test, feature = "mock"))]
+pub use mock::{event_name, MockHost, Output};
+
+
+/// The functions of HexChat used by the plugin. Channels are always identified
+///     by their names on the Twitch network.
+pub trait Host {
+    /// Print a line of plain text in the current Channel.
+    fn print_plain(&self, text: &str);
+    /// Print a [`PrintEvent`] in the current Channel.
+    fn print_event(&self, event: PrintEvent, args: &[&str]);
+    /// Print a [`PrintEvent`] in a specific Channel. Returns `false` if there
+    ///     is no tab for the Channel.
+    fn print_event_to(&self, channel: &str, event: PrintEvent, args: &[&str]) -> bool;
+    /// Execute a command, as if it had been typed into the current Channel.
+    fn send_command(&self, command: &str);
+    /// Remove colors and other formatting codes from text.
+    fn strip_formatting(&self, text: &str) -> Result<String, ()>;
+
+    /// Name of the current Channel.
+    fn get_channel_name(&self) -> String;
+    /// Name of the current network, if there is one.
+    fn get_network_name(&self) -> Option<String>;
+    /// Check whether a tab is open for a Channel.
+    fn has_channel(&self, channel: &str) -> bool;
+    /// Check whether the current Channel is also the one the user is viewing.
+    fn is_focused(&self) -> bool;
+    /// Topic of a Channel, if it has a tab and a topic.
+    fn get_topic(&self, channel: &str) -> Option<String>;
+    /// Name of the user on the current network.
+    fn get_nickname(&self) -> String;
+    /// Directory in which HexChat keeps its configuration.
+    fn get_config_dir(&self) -> PathBuf;
+
+    fn get_pref_int(&self, name: &str) -> Option<u32>;
+    fn set_pref_int(&self, name: &str, value: u32) -> Result<(), ()>;
+    fn get_pref_string(&self, name: &str) -> Option<String>;
+    fn set_pref_string(&self, name: &str, value: &str) -> Result<(), ()>;
+    fn delete_pref(&self, name: &str) -> Result<(), ()>;
+    /// Names of every preference stored by the plugin.
+    fn get_prefs(&self) -> Vec<String>;
+}
+
+
+/// The real HexChat, as seen through the `hexchat` crate.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HexChat;
+
+impl Host for HexChat {
+    fn print_plain(&self, text: &str) { hexchat::print_plain(text) }
+
+    fn print_event(&self, event: PrintEvent, args: &[&str]) {
+        hexchat::print_event(event, args)
+    }
+
+    fn print_event_to(&self, channel: &str, event: PrintEvent, args: &[&str]) -> bool {
+        match hexchat::get_channel(NETWORK, channel) {
+            Some(cref) => {
+                hexchat::print_event_to_channel(&cref, event, args);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn send_command(&self, command: &str) { hexchat::send_command(command) }
+
+    fn strip_formatting(&self, text: &str) -> Result<String, ()> {
+        hexchat::strip_formatting(text)
+    }
+
+    fn get_channel_name(&self) -> String { hexchat::get_channel_name() }
+
+    fn get_network_name(&self) -> Option<String> { hexchat::get_network_name() }
+
+    fn has_channel(&self, channel: &str) -> bool {
+        hexchat::get_channel(NETWORK, channel).is_some()
+    }
+
+    fn is_focused(&self) -> bool {
+        hexchat::get_focused_channel() == Some(hexchat::get_current_channel())
+    }
+
+    fn get_topic(&self, channel: &str) -> Option<String> {
+        hexchat::get_topic(&hexchat::get_channel(NETWORK, channel)?)
+    }
+
+    fn get_nickname(&self) -> String { hexchat::get_nickname() }
+
+    fn get_config_dir(&self) -> PathBuf { hexchat::get_config_dir() }
+
+    fn get_pref_int(&self, name: &str) -> Option<u32> {
+        hexchat::get_pref_int(name)
+    }
+
+    fn set_pref_int(&self, name: &str, value: u32) -> Result<(), ()> {
+        hexchat::set_pref_int(name, value)
+    }
+
+    fn get_pref_string(&self, name: &str) -> Option<String> {
+        hexchat::get_pref_string(name)
+    }
+
+    fn set_pref_string(&self, name: &str, value: &str) -> Result<(), ()> {
+        hexchat::set_pref_string(name, value)
+    }
+
+    fn delete_pref(&self, name: &str) -> Result<(), ()> {
+        hexchat::delete_pref(name)
+    }
+
+    fn get_prefs(&self) -> Vec<String> { hexchat::get_prefs() }
+}
+
+
+thread_local! {
+    static HOST: RefCell<Rc<dyn Host>> = RefCell::new(Rc::new(HexChat));
+}
+
+
+/// Replace the Host for the current thread, returning the previous one.
+///
+/// Input: `Rc<dyn Host>`
+/// Return: `Rc<dyn Host>`
+pub fn set_host(host: Rc<dyn Host>) -> Rc<dyn Host> {
+    HOST.with(|cell| cell.replace(host))
+}
+
+
+/// Get the Host for the current thread. The Host is cloned out of its cell, so
+///     that it may safely be replaced by something it calls.
+///
+/// Return: `Rc<dyn Host>`
+pub fn host() -> Rc<dyn Host> {
+    HOST.with(|cell| cell.borrow().clone())
+}
+
+
+pub fn print_plain(text: &str) { host().print_plain(text) }
+
+pub fn print_event(event: PrintEvent, args: &[impl AsRef<str>]) {
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+    host().print_event(event, &args)
+}
+
+pub fn print_event_to(channel: &str, event: PrintEvent, args: &[impl AsRef<str>]) -> bool {
+    let args: Vec<&str> = args.iter().map(AsRef::as_ref).collect();
+    host().print_event_to(channel, event, &args)
+}
+
+pub fn send_command(command: &str) { host().send_command(command) }
+
+pub fn strip_formatting(text: &str) -> Result<String, ()> {
+    host().strip_formatting(text)
+}
+
+pub fn get_channel_name() -> String { host().get_channel_name() }
+
+pub fn get_network_name() -> Option<String> { host().get_network_name() }
+
+pub fn has_channel(channel: &str) -> bool { host().has_channel(channel) }
+
+pub fn is_focused() -> bool { host().is_focused() }
+
+pub fn get_topic(channel: &str) -> Option<String> { host().get_topic(channel) }
+
+pub fn get_nickname() -> String { host().get_nickname() }
+
+pub fn get_config_dir() -> PathBuf { host().get_config_dir() }
+
+pub fn get_pref_int(name: &str) -> Option<u32> { host().get_pref_int(name) }
+
+pub fn set_pref_int(name: &str, value: u32) -> Result<(), ()> {
+    host().set_pref_int(name, value)
+}
+
+pub fn get_pref_string(name: &str) -> Option<String> {
+    host().get_pref_string(name)
+}
+
+pub fn set_pref_string(name: &str, value: &str) -> Result<(), ()> {
+    host().set_pref_string(name, value)
+}
+
+pub fn delete_pref(name: &str) -> Result<(), ()> { host().delete_pref(name) }
+
+pub fn get_prefs() -> Vec<String> { host().get_prefs() }
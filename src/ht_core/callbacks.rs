@@ -1,47 +1,185 @@
-use std::time::{Duration, Instant};
+//! Callbacks waiting for a response from the server, in the form of a NOTICE.
+//!
+//! Any number of Callbacks may be pending at once. Each one has a [`Matcher`]
+//!     to select the NOTICE it is waiting for, and expires on its own schedule,
+//!     so that an unrelated NOTICE can never consume it.
+
+#[cfg(feature = "fake-modes")]
+use std::time::Duration;
+use std::time::Instant;
 use hexchat::EatMode;
 use parking_lot::Mutex;
 
 
 safe_static! {
-    static lazy CALLBACK: Mutex<Option<Callback>> = Default::default();
+    static lazy CALLBACKS: Mutex<Callbacks> = Default::default();
+}
+
+
+/// A NOTICE received from the server.
+pub struct Notice<'a> {
+    /// Channel in which the NOTICE was received.
+    pub channel: &'a str,
+    /// Text of the NOTICE.
+    pub text: &'a str,
+    /// Value of the `msg-id` Tag, if the NOTICE had one.
+    pub msg_id: Option<&'a str>,
+}
+
+
+/// A way to recognize a specific NOTICE.
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// The NOTICE has one of these values for its `msg-id` Tag.
+    MsgId(&'static [&'static str]),
+}
+
+
+/// A Channel, with a [`Pattern`] that NOTICEs received there must match.
+#[derive(Clone, Debug)]
+pub struct Matcher {
+    pub channel: String,
+    pub pattern: Pattern,
 }
 
+impl Matcher {
+    #[cfg(any(test, feature = "fake-modes"))]
+    pub fn msg_id(channel: impl Into<String>, ids: &'static [&'static str]) -> Self {
+        Self { channel: channel.into(), pattern: Pattern::MsgId(ids) }
+    }
+
+    fn matches(&self, notice: &Notice) -> bool {
+        self.channel.eq_ignore_ascii_case(notice.channel) && match self.pattern {
+            Pattern::MsgId(ids) => notice.msg_id.is_some_and(|id| ids.contains(&id)),
+        }
+    }
+}
 
-#[allow(dead_code)]
-pub fn register(callback: impl FnOnce(&String) -> EatMode + Send + 'static) {
-    *CALLBACK.lock() = Some(Callback::new(callback));
+
+/// Register a Callback to be run on the first NOTICE matched by a [`Matcher`],
+///     if one arrives before the timeout.
+///
+/// Input: [`Matcher`], `Duration`, `impl FnOnce(&Notice) -> EatMode`
+#[cfg(feature = "fake-modes")]
+pub fn register(
+    matcher: Matcher,
+    timeout: Duration,
+    callback: impl FnOnce(&Notice) -> EatMode + Send + 'static,
+) {
+    CALLBACKS.lock().push(matcher, Instant::now() + timeout, callback);
 }
 
 
-pub fn run(input: &String) -> EatMode {
-    match CALLBACK.lock().take() {
-        Some(cb) if cb.is_valid() => cb.run(input),
-        _ => EatMode::None,
+/// Run the oldest pending Callback that matches a NOTICE, if there is one.
+///
+/// Input: [`Notice`]
+/// Return: `EatMode`
+pub fn run(notice: &Notice) -> EatMode {
+    let found: Option<Callback> = CALLBACKS.lock().take(notice, Instant::now());
+
+    //  The lock must be released before the Callback runs, in case it wants to
+    //      register another.
+    match found {
+        Some(cb) => cb.run(notice),
+        None => EatMode::None,
     }
 }
 
 
 struct Callback {
-    callback: Box<dyn FnOnce(&String) -> EatMode + Send + 'static>,
-    registered: Instant,
+    callback: Box<dyn FnOnce(&Notice) -> EatMode + Send + 'static>,
+    matcher: Matcher,
+    expires: Instant,
 }
 
 impl Callback {
-    const TIMEOUT: Duration = Duration::from_secs(5);
+    fn is_valid(&self, now: Instant) -> bool {
+        now <= self.expires
+    }
 
-    fn new(callback: impl FnOnce(&String) -> EatMode + Send + 'static) -> Self {
-        Self {
-            callback: Box::new(callback),
-            registered: Instant::now(),
-        }
+    fn run(self, notice: &Notice) -> EatMode {
+        (self.callback)(notice)
     }
+}
 
-    fn is_valid(&self) -> bool {
-        self.registered.elapsed() <= Self::TIMEOUT
+
+/// The pending Callbacks, oldest first.
+#[derive(Default)]
+struct Callbacks { pending: Vec<Callback> }
+
+impl Callbacks {
+    #[cfg(any(test, feature = "fake-modes"))]
+    fn push(
+        &mut self,
+        matcher: Matcher,
+        expires: Instant,
+        callback: impl FnOnce(&Notice) -> EatMode + Send + 'static,
+    ) {
+        self.pending.push(Callback { callback: Box::new(callback), matcher, expires });
+    }
+
+    /// Remove the oldest Callback that matches a NOTICE and has not expired by
+    ///     the given time. Any that have expired are dropped.
+    fn take(&mut self, notice: &Notice, now: Instant) -> Option<Callback> {
+        self.pending.retain(|cb| cb.is_valid(now));
+
+        self.pending.iter()
+            .position(|cb| cb.matcher.matches(notice))
+            .map(|i| self.pending.remove(i))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{Arc, atomic::{AtomicUsize, Ordering}},
+        time::Duration,
+    };
+    use super::*;
+
+    fn notice<'a>(channel: &'a str, text: &'a str, msg_id: Option<&'a str>) -> Notice<'a> {
+        Notice { channel, text, msg_id }
     }
 
-    fn run(self, input: &String) -> EatMode {
-        (self.callback)(input)
+    fn run(callbacks: &mut Callbacks, notice: &Notice, now: Instant) -> EatMode {
+        match callbacks.take(notice, now) {
+            Some(cb) => cb.run(notice),
+            None => EatMode::None,
+        }
+    }
+
+    #[test]
+    fn test_callbacks() {
+        let mut callbacks = Callbacks::default();
+        let now: Instant = Instant::now();
+        let expires: Instant = now + Duration::from_secs(5);
+        let hits = Arc::new(AtomicUsize::new(0));
+
+        for channel in ["#asdf", "#qwert"] {
+            let hits = hits.clone();
+            callbacks.push(Matcher::msg_id(channel, &["room_mods"]), expires, move |n| {
+                assert_eq!(n.channel, channel);
+                hits.fetch_add(1, Ordering::SeqCst);
+                EatMode::All
+            });
+        }
+
+        callbacks.push(Matcher::msg_id("#asdf", &["no_mods"]), now, |_| EatMode::All);
+
+        //  Unrelated NOTICEs do not consume anything.
+        assert!(matches!(run(&mut callbacks, &notice("#asdf", "Hi.", Some("host_on")), now), EatMode::None));
+        assert!(matches!(run(&mut callbacks, &notice("#zxcv", "", Some("room_mods")), now), EatMode::None));
+
+        //  Each Channel gets its own response.
+        assert!(matches!(run(&mut callbacks, &notice("#qwert", "", Some("room_mods")), now), EatMode::All));
+        assert!(matches!(run(&mut callbacks, &notice("#asdf", "", Some("room_mods")), now), EatMode::All));
+        assert!(matches!(run(&mut callbacks, &notice("#asdf", "", Some("room_mods")), now), EatMode::None));
+        assert_eq!(hits.load(Ordering::SeqCst), 2);
+
+        //  Expired Callbacks are never run, and are dropped.
+        let later: Instant = now + Duration::from_millis(1);
+        assert!(matches!(run(&mut callbacks, &notice("#asdf", "", Some("no_mods")), later), EatMode::None));
+        assert!(callbacks.pending.is_empty());
     }
 }
@@ -0,0 +1,187 @@
+//! Capture of raw IRC lines to a file, to be attached to bug reports.
+//!
+//! Every line received from Twitch is written exactly as it arrived, one per
+//!     line, unless a filter is given. Lines that cannot be parsed are always
+//!     written, since they are the most likely to be the subject of a report.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+use parking_lot::Mutex;
+use regex::{Regex, RegexBuilder};
+use crate::irc::MessageRef;
+
+
+safe_static! {
+    static lazy CAPTURE: Mutex<Option<Capture>> = Default::default();
+}
+
+
+/// Replacement for an OAuth token in a redacted line.
+const REDACTED_TOKEN: &str = "oauth:REDACTED";
+/// Replacement for the name of the current User in a redacted line.
+const REDACTED_USER: &str = "REDACTED_USER";
+
+
+/// Patterns to be removed from captured lines.
+#[derive(Debug)]
+pub struct Redaction {
+    token: Regex,
+    user: Option<Regex>,
+}
+
+impl Redaction {
+    /// Prepare to redact OAuth tokens and, if it is known, the name of the
+    ///     current User.
+    pub fn new(username: Option<&str>) -> Self {
+        Self {
+            token: Regex::new(r"(?i)oauth:[a-z0-9]+").unwrap(),
+            user: username.filter(|name| !name.is_empty()).map(|name| {
+                RegexBuilder::new(&format!(r"\b{}\b", regex::escape(name)))
+                    .case_insensitive(true)
+                    .build()
+                    .unwrap()
+            }),
+        }
+    }
+
+    pub fn apply(&self, line: &str) -> String {
+        let line = self.token.replace_all(line, REDACTED_TOKEN);
+
+        match &self.user {
+            Some(user) => user.replace_all(&line, REDACTED_USER).into_owned(),
+            None => line.into_owned(),
+        }
+    }
+}
+
+
+/// A capture in progress.
+#[derive(Debug)]
+pub struct Capture {
+    pub path: PathBuf,
+    file: File,
+    /// Commands or `msg-id` values to be captured. If empty, every line is.
+    pub filters: Vec<String>,
+    pub redaction: Option<Redaction>,
+    /// The number of lines written so far.
+    pub lines: usize,
+}
+
+impl Capture {
+    fn wants(&self, msg: &MessageRef) -> bool {
+        self.filters.is_empty() || self.filters.iter().any(|filter| {
+            msg.command.eq_ignore_ascii_case(filter)
+                || msg.get_tag_raw("msg-id") == Some(filter.as_str())
+        })
+    }
+
+    fn write(&mut self, raw: &str) -> io::Result<()> {
+        match &self.redaction {
+            Some(redaction) => writeln!(self.file, "{}", redaction.apply(raw))?,
+            None => writeln!(self.file, "{}", raw)?,
+        }
+
+        self.lines += 1;
+        Ok(())
+    }
+
+    /// Describe the capture in a line of text.
+    pub fn describe(&self) -> String {
+        let mut desc: String = format!(
+            "{} line(s) captured to {}",
+            self.lines,
+            self.path.display(),
+        );
+
+        if !self.filters.is_empty() {
+            desc.push_str(&format!(", filtered by {}", self.filters.join(", ")));
+        }
+
+        if self.redaction.is_some() {
+            desc.push_str(", redacted");
+        }
+
+        desc
+    }
+}
+
+
+/// Begin capturing lines to a file, which is appended to if it exists. Any
+///     capture already in progress is stopped.
+///
+/// Input: `PathBuf`, `Vec<String>`, `Option<Redaction>`
+/// Return: `io::Result<()>`
+pub fn start(
+    path: PathBuf,
+    filters: Vec<String>,
+    redaction: Option<Redaction>,
+) -> io::Result<()> {
+    let file: File = OpenOptions::new().create(true).append(true).open(&path)?;
+
+    *CAPTURE.lock() = Some(Capture { path, file, filters, redaction, lines: 0 });
+    Ok(())
+}
+
+
+/// Stop the current capture, returning a description of it, if there was one.
+///
+/// Return: `Option<String>`
+pub fn stop() -> Option<String> {
+    CAPTURE.lock().take().map(|capture| capture.describe())
+}
+
+
+/// Describe the current capture, if there is one.
+///
+/// Return: `Option<String>`
+pub fn status() -> Option<String> {
+    CAPTURE.lock().as_ref().map(Capture::describe)
+}
+
+
+/// Write a line to the current capture, if there is one and the line passes
+///     its filter. If the line cannot be written, the capture is stopped.
+///
+/// Input: `Option<&MessageRef>`, `&str`
+/// Return: `Option<io::Error>`
+pub fn tee(msg: Option<&MessageRef>, raw: &str) -> Option<io::Error> {
+    let mut guard = CAPTURE.lock();
+    let capture: &mut Capture = guard.as_mut()?;
+
+    if msg.is_none_or(|msg| capture.wants(msg)) {
+        if let Err(e) = capture.write(raw) {
+            *guard = None;
+            return Some(e);
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction() {
+        let redaction = Redaction::new(Some("AsdfQwert"));
+
+        assert_eq!(
+            redaction.apply("PASS oauth:0123456789abcdefghijABCDEFGHIJ"),
+            "PASS oauth:REDACTED",
+        );
+        assert_eq!(
+            redaction.apply(
+                "@display-name=AsdfQwert;login=asdfqwert :tmi.twitch.tv \
+                USERNOTICE #zxcv :asdfqwert2 says hi to asdfqwert",
+            ),
+            "@display-name=REDACTED_USER;login=REDACTED_USER :tmi.twitch.tv \
+            USERNOTICE #zxcv :asdfqwert2 says hi to REDACTED_USER",
+        );
+        assert_eq!(Redaction::new(None).apply("asdfqwert"), "asdfqwert");
+    }
+}
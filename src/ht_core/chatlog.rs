@@ -0,0 +1,207 @@
+//! Structured logging of Twitch chat, as JSON Lines.
+//!
+//! HexChat keeps its own logs, but only of the text it prints, which loses the
+//!     Tags of every Message. When enabled, this module writes each line in a
+//!     Channel to `<config>/hextwitch/logs/<channel>/<date>.jsonl`, with its
+//!     Tags and whatever the plugin made of it. A new file is begun each day.
+
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    fs::{create_dir_all, File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use parking_lot::Mutex;
+use serde_json::{json, Map, Value};
+use crate::{
+    host::get_config_dir,
+    irc::{MessageRef, unescape},
+    twitch::{timestamp, TwitchEvent},
+};
+use super::output::{alert_error, badge_parse};
+
+
+const DIR_LOGS: [&str; 2] = ["hextwitch", "logs"];
+/// Name of the directory for Whispers, which do not belong to any Channel.
+const DIR_WHISPERS: &str = "@whispers";
+
+
+safe_static! {
+    static lazy LOGS: Mutex<HashMap<String, LogFile>> = Default::default();
+}
+
+
+/// An open log file, and the day it is for.
+struct LogFile {
+    date: String,
+    file: File,
+}
+
+
+/// Open the log file for a Channel on a given day, creating it if needed.
+fn open(dir: &str, date: &str) -> std::io::Result<File> {
+    let mut path: PathBuf = get_config_dir();
+    path.extend(DIR_LOGS);
+    path.push(dir);
+    create_dir_all(&path)?;
+
+    path.push(format!("{}.jsonl", date));
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+
+/// Get the name of the directory for the log of a Channel. Returns `None` if
+///     the name could reach outside of the log directory, or is otherwise not
+///     safe to use as a path.
+fn channel_dir(channel: &str) -> Option<&str> {
+    let name: &str = channel.strip_prefix('#')?;
+
+    if name.is_empty()
+        || name.contains("..")
+        || name.contains(['/', '\\', ':', '\0'])
+    {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+
+/// Describe a line as a single JSON object.
+fn entry(msg: &MessageRef, raw: &str, time: DateTime<Utc>) -> String {
+    let tags: Map<String, Value> = msg.tags()
+        .map(|(key, value)| (key.to_owned(), value.map(unescape).into()))
+        .collect();
+
+    let badges: Option<String> = msg.get_tag("badges").and_then(|badges| {
+        let info: String = msg.get_tag("badge-info").unwrap_or_default();
+        badge_parse(badges, info).output
+    });
+
+    let event: Option<Value> = TwitchEvent::try_from(&msg.to_message()).ok()
+        .and_then(|event| serde_json::to_value(event).ok());
+
+    json!({
+        "time": time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        "command": msg.command,
+        "channel": msg.arg(0),
+        "user": msg.author(),
+        "text": msg.trail,
+        "tags": tags,
+        "badges": badges,
+        "event": event,
+        "raw": raw,
+    }).to_string()
+}
+
+
+/// Append a line to the log for its Channel. Lines that are not in a Channel,
+///     other than Whispers, are not logged, and neither are lines in Channels
+///     whose names are not safe to use as a path.
+///
+/// Input: `&MessageRef`, `&str`
+pub fn log(msg: &MessageRef, raw: &str) {
+    let dir: &str = match msg.arg(0) {
+        Some(channel) if channel.starts_with('#') => match channel_dir(channel) {
+            Some(dir) => dir,
+            None => return,
+        },
+        _ if msg.command == "WHISPER" => DIR_WHISPERS,
+        _ => return,
+    };
+
+    let time: DateTime<Utc> = msg.get_tag_raw("tmi-sent-ts")
+        .and_then(timestamp)
+        .unwrap_or_else(Utc::now);
+    let date: String = time.with_timezone(&Local).format("%Y-%m-%d").to_string();
+    let line: String = entry(msg, raw, time);
+
+    let mut logs = LOGS.lock();
+
+    //  The file for the previous day is closed when it is replaced, so there
+    //      is never more than one open for each Channel.
+    if logs.get(dir).is_none_or(|log| log.date != date) {
+        match open(dir, &date) {
+            Ok(file) => { logs.insert(dir.to_owned(), LogFile { date, file }); }
+            Err(e) => {
+                drop(logs);
+                alert_error(format!("Failed to open chat log: {}", e));
+                return;
+            }
+        }
+    }
+
+    if let Some(log) = logs.get_mut(dir) {
+        if let Err(e) = writeln!(log.file, "{}", line) {
+            //  Forget the file, so that it is opened again for the next line,
+            //      in case the problem was only brief.
+            logs.remove(dir);
+            drop(logs);
+            alert_error(format!("Failed to write chat log: {}", e));
+        }
+    }
+}
+
+
+/// Close every open log file.
+pub fn close_all() {
+    LOGS.lock().clear();
+}
+
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use super::*;
+
+    #[test]
+    fn test_channel_dir() {
+        assert_eq!(channel_dir("#asdf_qwert"), Some("asdf_qwert"));
+        assert_eq!(channel_dir("asdf"), None);
+        assert_eq!(channel_dir("#"), None);
+        assert_eq!(channel_dir("#.."), None);
+        assert_eq!(channel_dir("#../../etc"), None);
+        assert_eq!(channel_dir("#a/b"), None);
+        assert_eq!(channel_dir("#a\\b"), None);
+        assert_eq!(channel_dir("#c:"), None);
+    }
+
+    #[test]
+    fn test_entry() {
+        let raw: &str = "@badge-info=subscriber/14;badges=subscriber/12;\
+            display-name=Asdf;id=abc-123;login=asdf;msg-id=raid;\
+            msg-param-displayName=Asdf;msg-param-viewerCount=9;room-id=1;\
+            tmi-sent-ts=1600000000000;user-id=42 \
+            :tmi.twitch.tv USERNOTICE #qwert :hello there";
+        let msg = MessageRef::parse(raw).unwrap();
+        let time: DateTime<Utc> = Utc.timestamp_millis_opt(1600000000000).unwrap();
+
+        let line: String = entry(&msg, raw, time);
+        let value: Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(value["time"], "2020-09-13T12:26:40.000Z");
+        assert_eq!(value["command"], "USERNOTICE");
+        assert_eq!(value["channel"], "#qwert");
+        assert_eq!(value["text"], "hello there");
+        assert_eq!(value["tags"]["login"], "asdf");
+        assert_eq!(value["raw"], raw);
+
+        //  The event is given as an object, rather than as a string.
+        let event: &Value = &value["event"];
+        assert!(event.is_object());
+        assert_eq!(event["type"], "raid");
+        assert_eq!(event["meta"]["channel"], "#qwert");
+        assert_eq!(event["meta"]["room_id"], 1);
+        assert_eq!(event["user"]["login"], "asdf");
+        assert_eq!(event["user"]["id"], 42);
+        assert_eq!(event["viewers"], 9);
+
+        //  A line that is not a Twitch event has no event at all.
+        let raw: &str = ":asdf!asdf@asdf.tmi.twitch.tv JOIN #qwert";
+        let msg = MessageRef::parse(raw).unwrap();
+        let value: Value = serde_json::from_str(&entry(&msg, raw, time)).unwrap();
+        assert!(value["event"].is_null());
+    }
+}
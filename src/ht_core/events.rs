@@ -1,16 +1,21 @@
-use std::{collections::HashMap, fmt::Write};
-use hexchat::{
-    ChannelRef,
-    EatMode,
-    get_channel,
-    get_channel_name,
-    get_current_channel,
-    get_focused_channel,
-    print_event_to_channel,
-    print_plain,
-    PrintEvent,
+use std::{convert::TryFrom, fmt::Write, time::Duration};
+use chrono::Utc;
+use hexchat::{EatMode, PrintEvent};
+use crate::{
+    host::{
+        get_channel_name,
+        get_nickname,
+        has_channel,
+        is_focused,
+        print_event_to,
+        print_plain,
+    },
+    irc::{Message, split_at_char, Tags},
+    NETWORK,
+    prefs::*,
+    twitch::{NoticeId, Number, SubDetails, SubPlan, TwitchEvent, Upgrade},
 };
-use crate::{irc::{Message, split_at_char}, NETWORK, prefs::*};
+use super::{gifts, ratelimit::{self, Traffic}};
 use super::output::{
     alert_basic,
     alert_error,
@@ -22,7 +27,10 @@ use super::output::{
     EVENT_ALERT,
     EVENT_CHANNEL,
     EVENT_REWARD,
+    history::HistoryEntry,
+    NOTICES_UNKNOWN,
     print_announcement,
+    stats::{StatEvent, StatKind},
     TabColor,
     USERSTATE,
 };
@@ -36,8 +44,16 @@ const ME: &str = "/me ";
 ///     avoid using a "magic number" to slice off the prefix.
 const ME_LEN: usize = ME.len();
 
+/// The number of recent messages to show from a User who is banned or timed
+///     out.
+const CLEAR_CONTEXT: usize = 3;
 
-pub fn cheer(name: &str, number: usize) {
+/// The number of seconds to wait after a gift bomb before listing recipients.
+const GIFT_LIST_DELAY: u32 = 10;
+
+
+
+pub fn cheer(name: &str, number: u32) {
     if number > 0 {
         echo(EVENT_REWARD, &[
             "CHEER",
@@ -48,8 +64,16 @@ pub fn cheer(name: &str, number: usize) {
 }
 
 
-pub fn reward(word: &[String], msg: &Message) -> Option<EatMode> {
+/// Print a message which redeems a Custom Reward, or which is highlighted. The
+///     marker for a new or returning chatter is put in front of its text.
+pub fn reward(
+    word: &[String],
+    msg: &Message,
+    marker: &str,
+    color: TabColor,
+) -> Option<EatMode> {
     const REWARD_UNKNOWN: &str = "CUSTOM";
+    let text: String = format!("{}{}", marker, word[1]);
 
     if let Some(id) = msg.get_tag("custom-reward-id") {
         //  This Message is a Custom Reward.
@@ -105,21 +129,119 @@ pub fn reward(word: &[String], msg: &Message) -> Option<EatMode> {
         echo(EVENT_REWARD, &[
             reward_name,
             &author_name,
-            &word[1],
-        ], TabColor::Message);
+            &text,
+        ], color);
 
         Some(EatMode::All)
     } else if "highlighted-message" == msg.get_tag("msg-id")? {
         echo(EVENT_ALERT, &[
             msg.author(),
-            &word[1],
-        ], TabColor::Message);
+            &text,
+        ], color);
 
         Some(EatMode::All)
     } else { None }
 }
 
 
+/// Explain a NOTICE from Twitch, if its `msg-id` is known. Unknown IDs are
+///     collected for `/UNKNOWNS`, and those NOTICEs are left for HexChat.
+pub fn notice(msg: Message) -> Option<EatMode> {
+    let (meta, msg_id, text) = match TwitchEvent::try_from(&msg).ok()? {
+        TwitchEvent::Notice { meta, msg_id, text } => (meta, msg_id, text),
+        _ => return None,
+    };
+    let id: NoticeId = match msg_id {
+        Some(id) => id,
+        None => return Some(EatMode::None),
+    };
+    let color: TabColor = notice_color(&id);
+
+    let (followers, delay, last_sent) = match CHANNELS.get(&meta.channel) {
+        Some(data) => (data.roomstate.followers, data.roomstate.slow, data.last_sent),
+        None => (FollowMode::Off, None, None),
+    };
+    let delay: Option<Duration> = delay.map(|sec| Duration::from_secs(sec as u64));
+    let remaining: Option<Duration> = delay.zip(last_sent)
+        .map(|(delay, sent)| delay.saturating_sub(sent.elapsed()))
+        .filter(|left| !left.is_zero());
+
+    let (header, explanation): (&str, String) = match id {
+        NoticeId::Duplicate => ("NOT SENT", String::from(
+            "Your message was identical to the one before it. Twitch does not \
+            allow the same message to be sent twice within 30 seconds.",
+        )),
+        NoticeId::SlowMode => ("SLOW MODE", match (remaining, delay) {
+            (Some(left), _) => format!(
+                "Your message was not sent. You may speak again in {}s.",
+                left.as_secs() + 1,
+            ),
+            (None, Some(delay)) => format!(
+                "Your message was not sent. Only one message may be sent every {}s.",
+                delay.as_secs(),
+            ),
+            (None, None) => text,
+        }),
+        NoticeId::FollowersOnly => ("FOLLOWERS", match followers {
+            FollowMode::ForMinutes(min) if min > 0 => format!(
+                "Your message was not sent. Only Users who have followed this \
+                Channel for {} minute{} may chat.",
+                min, if min == 1 { "" } else { "s" },
+            ),
+            _ => String::from(
+                "Your message was not sent. Only followers of this Channel may chat.",
+            ),
+        }),
+        NoticeId::SubsOnly => ("SUBSCRIBERS", String::from(
+            "Your message was not sent. Only subscribers to this Channel may chat.",
+        )),
+        NoticeId::EmoteOnly => ("EMOTES", String::from(
+            "Your message was not sent. Only Emotes may be used in this Channel.",
+        )),
+        NoticeId::Unique => ("UNIQUE", String::from(
+            "Your message was not sent. Messages in this Channel must be unique.",
+        )),
+        NoticeId::Banned => ("BANNED", String::from(
+            "Your message was not sent. You are banned from this Channel.",
+        )),
+        NoticeId::TimedOut => ("TIMED OUT", text),
+        NoticeId::RateLimit => ("RATE LIMIT", String::from(
+            "Your message was not sent, because too many were sent too quickly. \
+            Wait a moment before trying again.",
+        )),
+        NoticeId::RequiresVerifiedPhone => ("PHONE", String::from(
+            "Your message was not sent. This Channel requires a verified phone \
+            number, which may be added in the Security settings of your \
+            Twitch account.",
+        )),
+        NoticeId::UnrecognizedCmd => ("COMMAND", format!(
+            "{} Many commands may now only be used on the Twitch website.",
+            text,
+        )),
+        //  Responses to commands may be wanted by Callbacks.
+        NoticeId::Mods => return Some(EatMode::None),
+        NoticeId::Other(id) => {
+            NOTICES_UNKNOWN.add(id);
+            return Some(EatMode::None);
+        }
+    };
+
+    echo(EVENT_ALERT, &[header, &explanation], color);
+    Some(EatMode::Hexchat)
+}
+
+
+/// Color for the tab when a NOTICE is received. A message that was not sent
+///     is more important than most events.
+const fn notice_color(id: &NoticeId) -> TabColor {
+    match id {
+        NoticeId::Banned | NoticeId::TimedOut => TabColor::Highlight,
+        NoticeId::UnrecognizedCmd | NoticeId::Mods | NoticeId::Other(_) => TabColor::Event,
+        _ => TabColor::Message,
+    }
+}
+
+
 pub fn reconnect(msg: Message) -> Option<EatMode> {
     echo(PrintEvent::SERVER_NOTICE, &[
         "IRC Service is about to restart.",
@@ -131,12 +253,14 @@ pub fn reconnect(msg: Message) -> Option<EatMode> {
 
 
 pub fn roomstate(msg: Message) -> Option<EatMode> {
-    let tags: &HashMap<String, String> = msg.tags.as_ref()?;
+    let tags: &Tags = msg.tags.as_ref()?;
     let join: bool = tags.len() > 2;
     // let debug: bool = PREF_DEBUG.is(&true);
 
     let roomstate: &mut RoomState = &mut CHANNELS.current().roomstate;
-    let mut tags_vec: Vec<(&String, &String)> = tags.iter().collect();
+    let mut tags_vec: Vec<(&str, &str)> = tags.iter()
+        .map(|tag| (tag.key.as_str(), tag.value_raw()))
+        .collect();
     tags_vec.sort_unstable();
 
     for (k, v) in tags_vec {
@@ -198,229 +322,285 @@ pub fn roomstate(msg: Message) -> Option<EatMode> {
 }
 
 
+/// Names of the months, for rendering the end of an extended subscription.
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August",
+    "September", "October", "November", "December",
+];
+
+
+/// Write a description of a subscription plan. Tier 1 is the most common plan,
+///     so it may be left out.
+fn write_plan(line: &mut String, plan: &Option<SubPlan>, show_tier1: bool) {
+    match plan {
+        None => {}
+        Some(SubPlan::Prime) => line.push_str(" with Prime"),
+        Some(SubPlan::Tier1) if !show_tier1 => {}
+        Some(SubPlan::Tier1) => line.push_str(" at Tier 1 ($5)"),
+        Some(SubPlan::Tier2) => line.push_str(" at Tier 2 ($10)"),
+        Some(SubPlan::Tier3) => line.push_str(" at Tier 3 ($25)"),
+        Some(SubPlan::Other(plan)) => {
+            write!(line, " with plan {:?}", plan).ok();
+        }
+    }
+}
+
+
+/// Write the streak and cumulative months of a subscription, if either of them
+///     is more than one.
+fn write_months(line: &mut String, streak: Option<u32>, cumul: Option<u32>) {
+    if let Some(streak) = streak.filter(|&n| n > 1) {
+        write!(line, " for ({}) months in a row", streak).ok();
+    }
+
+    if let Some(cumul) = cumul.filter(|&n| n > 1) {
+        write!(line, ", with ({}) months in total", cumul).ok();
+    }
+}
+
+
+/// Write the message attached to an event, if there is one.
+fn write_text(line: &mut String, text: &Option<String>) {
+    if let Some(text) = text {
+        write!(line, ": {}", text).ok();
+    }
+}
+
+
+fn render_sub(
+    login: &str,
+    stype: &str,
+    sub: &SubDetails,
+    text: &Option<String>,
+) -> String {
+    // Maximum possible usage should be 362 bytes; 384=256+128
+    let mut line: String = String::with_capacity(384);
+    write!(&mut line, "<{}> {}scribes", login, stype).ok();
+
+    write_plan(&mut line, &sub.plan, true);
+    write_months(&mut line, sub.streak_months, sub.cumulative_months);
+    write_text(&mut line, text);
+
+    line
+}
+
+
+/// Count an event in the session statistics of its Channel, if it is one that
+///     should be counted.
+fn record_stats(event: &TwitchEvent) {
+    let (kind, user, amount, plan) = match event {
+        TwitchEvent::Sub { user, sub, .. }
+        => (StatKind::Sub, user, 1, sub.plan.as_ref()),
+        TwitchEvent::Resub { user, sub, .. }
+        => (StatKind::Resub, user, 1, sub.plan.as_ref()),
+        //  Every gift in a set arrives separately, so only the individual gifts
+        //      are counted, rather than the announcement of the set.
+        TwitchEvent::SubGift { user, plan, .. }
+        => (StatKind::Gift, user, 1, plan.as_ref()),
+        TwitchEvent::Raid { user, viewers, .. }
+        => (StatKind::Raid, user, viewers.valid().unwrap_or(0), None),
+        _ => return,
+    };
+
+    if let Some(meta) = event.meta() {
+        CHANNELS.ensure(meta.channel.clone()).stats.record(StatEvent {
+            kind,
+            time: meta.sent.unwrap_or_else(Utc::now),
+            user: &user.login,
+            amount,
+            plan,
+        });
+    }
+}
+
+
 pub fn usernotice(msg: Message) -> Option<EatMode> {
-    let stype: String = msg.get_tag("msg-id")?;
+    let event: TwitchEvent = TwitchEvent::try_from(&msg).ok()?;
+    record_stats(&event);
 
-    match stype.as_str() {
-        "announcement" => {
+    match event {
+        TwitchEvent::Announcement { .. } => {
             return print_announcement(msg);
         }
-        "raid" => {
+        TwitchEvent::Raid { from, viewers, .. } => {
             alert_basic(&format!(
                 "A raid of {} arrives from #{}",
-                msg.get_tag("msg-param-viewerCount")?,
-                msg.get_tag("msg-param-displayName")?.to_lowercase(),
+                viewers,
+                from.to_lowercase(),
             ));
         }
-        "charity" | "rewardgift" | "ritual" => {
-            alert_basic(&msg.get_tag("system-msg")?);
+        TwitchEvent::SystemMessage { text, .. } => {
+            alert_basic(&text);
         }
 
-        "bitsbadgetier" => {
-            let mut notif: String = match msg
-                .get_tag("msg-param-threshold")
-                .and_then(|t| t.parse::<usize>().ok())
-            {
+        TwitchEvent::BitsBadgeTier { user, threshold, text, .. } => {
+            let mut notif: String = match threshold {
                 Some(bits) => format!(
                     "<{}> earns a new tier of Bits Badge for cheering {} Bits \
                         (${:.2}) total",
-                    msg.get_tag("login")?, bits,
+                    user.login, bits,
                     bits as f64 * 0.01,
                 ),
                 None => format!(
                     "<{}> earns a new tier of Bits Badge",
-                    msg.get_tag("login")?,
+                    user.login,
                 ),
             };
 
-            if !msg.trail.is_empty() { write!(&mut notif, ": {}", msg.trail).ok()?; }
+            write_text(&mut notif, &text);
 
             echo(EVENT_ALERT, &["BADGE", &notif], TabColor::Event);
         }
 
-        "unraid" => alert_basic("A raid is canceled"),
-
-        "sub" | "resub" => {
-            // Maximum possible usage should be 362 bytes; 384=256+128
-            let mut line: String = String::with_capacity(384);
-            write!(&mut line, "<{}> {}scribes", msg.get_tag("login")?, stype).ok()?;
-
-            if let Some(plan) = msg.get_tag("msg-param-sub-plan") {
-                match plan.as_str() {
-                    "Prime" => line.push_str(" with Prime"),
-                    "1000" => line.push_str(" at Tier 1 ($5)"),
-                    "2000" => line.push_str(" at Tier 2 ($10)"),
-                    "3000" => line.push_str(" at Tier 3 ($25)"),
-                    plan => write!(&mut line, " with plan {:?}", plan).ok()?,
-                };
-            }
+        TwitchEvent::Unraid { .. } => alert_basic("A raid is canceled"),
 
-            if let Some(streak) = msg.get_tag("msg-param-streak-months") {
-                if streak.parse().unwrap_or(0) > 1 {
-                    write!(&mut line, " for ({}) months in a row", streak).ok()?;
-                }
-            }
+        TwitchEvent::Sub { user, sub, text, .. } => {
+            alert_subscription(render_sub(&user.login, "sub", &sub, &text));
+        }
+        TwitchEvent::Resub { user, sub, text, .. } => {
+            alert_subscription(render_sub(&user.login, "resub", &sub, &text));
+        }
 
-            if let Some(cumul) = msg.get_tag("msg-param-cumulative-months") {
-                if cumul.parse().unwrap_or(0) > 1 {
-                    write!(&mut line, ", with ({}) months in total", cumul).ok()?;
-                }
+        TwitchEvent::ExtendSub { user, sub, end_month, text, .. } => {
+            // Maximum possible usage should be 384 bytes.
+            let mut line: String = String::with_capacity(384);
+            write!(&mut line, "<{}> extends a sub", user.login).ok()?;
+
+            write_plan(&mut line, &sub.plan, false);
+            write_months(&mut line, sub.streak_months, sub.cumulative_months);
+
+            match end_month {
+                Some(Number::Valid(month @ 1..=12)) => write!(
+                    &mut line, ", through {}",
+                    MONTHS[month as usize - 1],
+                ).ok()?,
+                Some(other) => write!(&mut line, ", through {}", other).ok()?,
+                None => {}
             }
 
-            if !msg.trail.is_empty() { write!(&mut line, ": {}", msg.trail).ok()?; }
+            write_text(&mut line, &text);
 
             alert_subscription(&line);
         }
 
-        "extendsub" => {
-            // Maximum possible usage should be 384 bytes.
-            let mut line: String = String::with_capacity(384);
-            write!(&mut line, "<{}> extends a sub", msg.get_tag("login")?).ok()?;
-
-            if let Some(plan) = msg.get_tag("msg-param-sub-plan") {
-                match plan.as_str() {
-                    "Prime" => line.push_str(" with Prime"),
-                    "1000" => { /*line.push_str(" at Tier 1 ($5)")*/ }
-                    "2000" => line.push_str(" at Tier 2 ($10)"),
-                    "3000" => line.push_str(" at Tier 3 ($25)"),
-                    plan => write!(&mut line, " with plan {:?}", plan).ok()?,
-                };
-            }
+        TwitchEvent::SubGift {
+            user, recipient, months, cumulative_months, sender_count,
+            origin_id: Some(origin_id), ..
+        } if gifts::add_recipient(&origin_id, &user.login, &recipient.login) => {
+            //  This gift is part of a larger set, and has already been counted
+            //      in its summary.
+            if PREF_DEBUG.is(&true) {
+                let mut line: String = format!(
+                    "<{}> is gifted a subscription by <{}>",
+                    recipient.login,
+                    user.login,
+                );
 
-            if let Some(streak) = msg.get_tag("msg-param-streak-months") {
-                if streak.parse().unwrap_or(0) > 1 {
-                    write!(&mut line, " for ({}) months in a row", streak).ok()?;
+                if let Some(gifts) = sender_count.filter(|&n| n > 0) {
+                    write!(&mut line, " (Gifts: {})", gifts).ok()?;
                 }
-            }
 
-            if let Some(cumul) = msg.get_tag("msg-param-cumulative-months") {
-                if cumul.parse().unwrap_or(0) > 1 {
-                    write!(&mut line, ", with ({}) months in total", cumul).ok()?;
-                }
+                write_months(&mut line, months, cumulative_months);
+                alert_basic(&line);
             }
-
-            if let Some(month) = msg.get_tag("msg-param-sub-benefit-end-month") {
-                write!(&mut line, ", through {}", match month.as_str() {
-                    "1" => "January",
-                    "2" => "February",
-                    "3" => "March",
-                    "4" => "April",
-                    "5" => "May",
-                    "6" => "June",
-                    "7" => "July",
-                    "8" => "August",
-                    "9" => "September",
-                    "10" => "October",
-                    "11" => "November",
-                    "12" => "December",
-                    other => other,
-                }).ok()?;
-            }
-
-            if !msg.trail.is_empty() { write!(&mut line, ": {}", msg.trail).ok()?; }
-
-            alert_subscription(&line);
         }
-
-        "subgift" => {
+        TwitchEvent::SubGift {
+            user, recipient, months, cumulative_months, sender_count, ..
+        } => {
             let mut line: String = String::with_capacity(152);
             write!(
                 &mut line,
                 "<{}> is gifted a subscription by <{}>",
-                msg.get_tag("msg-param-recipient-user-name")?,
-                msg.get_tag("login")?,
+                recipient.login,
+                user.login,
             ).ok()?;
 
-            if let Some(gifts) = msg.get_tag("msg-param-sender-count") {
-                if gifts.parse().unwrap_or(0) > 0 {
-                    write!(&mut line, " (Gifts: {})", gifts).ok()?;
-                }
-            }
-
-            if let Some(streak) = msg.get_tag("msg-param-months") {
-                if streak.parse().unwrap_or(0) > 1 {
-                    write!(&mut line, " for ({}) months in a row", streak).ok()?;
-                }
+            if let Some(gifts) = sender_count.filter(|&n| n > 0) {
+                write!(&mut line, " (Gifts: {})", gifts).ok()?;
             }
 
-            if let Some(cumul) = msg.get_tag("msg-param-cumulative-months") {
-                if cumul.parse().unwrap_or(0) > 1 {
-                    write!(&mut line, ", with ({}) months in total", cumul).ok()?;
-                }
-            }
+            write_months(&mut line, months, cumulative_months);
 
             alert_subscription(&line);
         }
-        "submysterygift" => {
+        TwitchEvent::MysteryGift {
+            meta, user, count, sender_count, origin_id, ..
+        } => {
             let mut line: String = String::with_capacity(128);
-            let num: String = msg.get_tag("msg-param-mass-gift-count")?;
 
             write!(
                 &mut line,
                 "<{}> gives out ({}) random gift subscription{}",
-                msg.get_tag("login")?, num,
-                if &num == "1" { "" } else { "s" },
+                user.login, count,
+                if count == 1 { "" } else { "s" },
             ).ok()?;
 
-            if let Some(gifts) = msg.get_tag("msg-param-sender-count") {
-                if gifts.parse().unwrap_or(0) > 0 {
-                    write!(&mut line, " (Total: {})", gifts).ok()?;
-                }
+            if let Some(gifts) = sender_count.filter(|&n| n > 0) {
+                write!(&mut line, " (Total: {})", gifts).ok()?;
             }
 
             alert_subscription(&line);
+
+            //  Collect the individual gifts that follow, rather than printing
+            //      each one of them.
+            if let Some(origin_id) = origin_id.filter(|_| count > 1) {
+                if PREF_GIFT_LIST.is(&true) {
+                    cmd!("TIMER {} HTGIFTS {}", GIFT_LIST_DELAY, origin_id);
+                }
+
+                gifts::start(origin_id, meta.channel, user.login, count);
+            }
         }
-        "standardpayforward" => match msg.get_tag("msg-param-prior-gifter-user-name") {
-            Some(prior) => alert_basic(&format!(
-                "<{}> pays forward a gift subscription from <{}> to <{}>",
-                msg.get_tag("login")?,
-                prior,
-                msg.get_tag("msg-param-recipient-user-name")?,
-            )),
-            None => alert_basic(&format!(
-                "<{}> pays forward an anonymous gift subscription to <{}>",
-                msg.get_tag("login")?,
-                msg.get_tag("msg-param-recipient-user-name")?,
-            )),
+        TwitchEvent::PayForward { user, prior_gifter, recipient, .. } => {
+            match (prior_gifter, recipient) {
+                (Some(prior), Some(recipient)) => alert_basic(&format!(
+                    "<{}> pays forward a gift subscription from <{}> to <{}>",
+                    user.login, prior, recipient,
+                )),
+                (None, Some(recipient)) => alert_basic(&format!(
+                    "<{}> pays forward an anonymous gift subscription to <{}>",
+                    user.login, recipient,
+                )),
+                (Some(prior), None) => alert_basic(&format!(
+                    "<{}> pays forward a gift subscription from <{}> to the community",
+                    user.login, prior,
+                )),
+                (None, None) => alert_basic(&format!(
+                    "<{}> pays forward an anonymous gift subscription to the community",
+                    user.login,
+                )),
+            }
         }
-        "communitypayforward" => match msg.get_tag("msg-param-prior-gifter-user-name") {
-            Some(prior) => alert_basic(&format!(
-                "<{}> pays forward a gift subscription from <{}> to the community",
-                msg.get_tag("login")?,
-                prior,
+
+        TwitchEvent::SubUpgrade { user, from, .. } => match from {
+            Upgrade::Gift { sender } => alert_sub_upgrade(&format!(
+                "<{}> upgrades a gift subscription from <{}>",
+                user.login, sender,
+            )),
+            Upgrade::AnonGift => alert_sub_upgrade(&format!(
+                "<{}> upgrades an anonymous gift subscription",
+                user.login,
             )),
-            None => alert_basic(&format!(
-                "<{}> pays forward an anonymous gift subscription to the community",
-                msg.get_tag("login")?,
+            Upgrade::Prime => alert_sub_upgrade(&format!(
+                "<{}> upgrades a Prime subscription",
+                user.login,
             )),
         }
 
-        "giftpaidupgrade" => alert_sub_upgrade(&format!(
-            "<{}> upgrades a gift subscription from <{}>",
-            msg.get_tag("login")?,
-            msg.get_tag("msg-param-sender-login")?,
-        )),
-        "anongiftpaidupgrade" => alert_sub_upgrade(&format!(
-            "<{}> upgrades an anonymous gift subscription",
-            msg.get_tag("login")?,
-        )),
-        "primepaidupgrade" => alert_sub_upgrade(&format!(
-            "<{}> upgrades a Prime subscription",
-            msg.get_tag("login")?,
-        )),
-
-        _ => {
+        TwitchEvent::UnknownNotice { msg_id, system_msg, .. } => {
             if PREF_DEBUG.is(&true) {
                 alert_error(&format!(
                     "Unknown UserNotice ID {:?}: {}",
-                    stype, msg,
+                    msg_id, msg,
                 ));
             }
 
-            if let Some(sysmsg) = msg.get_tag("system-msg") {
+            if let Some(sysmsg) = system_msg {
                 echo(EVENT_ALERT, &["UNKNOWN", &sysmsg], TabColor::Event);
             }
         }
+
+        _ => return None,
     }
 
     Some(EatMode::Hexchat)
@@ -467,7 +647,7 @@ pub fn userstate(msg: Message) -> Option<EatMode> {
             echo(EVENT_REWARD, &[HEADER, phrase, ""], TabColor::None);
         } else {
             #[cfg(feature = "fake-modes")]
-            fake_mode_op(&channel, &hexchat::get_nickname(), badges.is_op());
+            fake_mode_op(&channel, &get_nickname(), badges.is_op());
 
             echo(EVENT_REWARD, &[
                 HEADER,
@@ -484,17 +664,13 @@ pub fn userstate(msg: Message) -> Option<EatMode> {
 
 
 /// Ensure Tab: Given a Channel Name, try to find it in the Twitch Network. If
-///     it is not found, run the Hexchat Command to open it. Then, try to find
-///     it again.
+///     it is not found, run the Hexchat Command to open it.
 ///
 /// Input: `&str`
-/// Return: `ChannelRef`
-/// Panics: If the Channel is not found after `QUERY` is executed.
-pub fn ensure_tab(name: &str) -> ChannelRef {
-    get_channel(NETWORK, &name).unwrap_or_else(|| {
+pub fn ensure_tab(name: &str) {
+    if !has_channel(name) {
         cmd!("QUERY {}", &name);
-        get_channel(NETWORK, &name).expect("Failed to ensure Whisper Tab.")
-    })
+    }
 }
 
 
@@ -528,8 +704,8 @@ pub fn whisper_recv(mut msg: Message) -> Option<EatMode> {
     //  Action Messages have a different format than simply a `/me` command. For
     //      example, the command "/me does something" would have to be changed
     //      to "\x01ACTION does something\x01".
-    if msg.trail.starts_with(ME) {
-        let text: &str = &msg.trail[ME_LEN..]; // Slice off the `/me `.
+    if msg.text().starts_with(ME) {
+        let text: &str = &msg.text()[ME_LEN..]; // Slice off the `/me `.
 
         //  If the Whisper Tab is not focused, also post it here.
         if PREF_WHISPERS.is(&true) && get_channel_name() != user {
@@ -537,13 +713,13 @@ pub fn whisper_recv(mut msg: Message) -> Option<EatMode> {
         }
 
         //  Format the sliced text into an Action Message and replace the Trail.
-        msg.trail = format!("\x01ACTION {}\x01", &text);
+        msg.trail = Some(format!("\x01ACTION {}\x01", &text));
     } else {
         //  If the Whisper Tab is not focused, also post it here.
         if PREF_WHISPERS.is(&true) && get_channel_name() != user {
             echo(
                 PrintEvent::PRIVATE_MESSAGE,
-                &[user, &msg.trail],
+                &[user, msg.text()],
                 TabColor::Message,
             );
         }
@@ -557,7 +733,14 @@ pub fn whisper_recv(mut msg: Message) -> Option<EatMode> {
 pub fn whisper_send_channel(etype: PrintEvent, channel: &str, word: &[String]) {
     //  Normal Message, does NOT begin with ".w". Need to send the Whisper.
     //      Execute SAY on the message with ".w" prepended.
-    if etype == PrintEvent::YOUR_ACTION {
+    let wait: Duration = ratelimit::wait(Traffic::Whisper, None);
+
+    if !wait.is_zero() {
+        alert_error(format!(
+            "Rate limit: Whisper NOT sent. Try again in {}s.",
+            wait.as_secs() + 1,
+        ));
+    } else if etype == PrintEvent::YOUR_ACTION {
         cmd!("SAY .w {} /me {}", channel, word[1]);
     } else {
         cmd!("SAY .w {} {}", channel, word[1]);
@@ -571,6 +754,8 @@ pub fn whisper_send_command(etype: PrintEvent, channel: &str, word: &[String]) {
     let (user, mut text) = split_at_char(word[1][3..].trim(), ' ');
 
     if !text.is_empty() {
+        ratelimit::record(Traffic::Whisper);
+
         if user != channel {
             //  If the current tab is not the target tab, also print a line here
             //      confirming the message is sent.
@@ -587,9 +772,11 @@ pub fn whisper_send_command(etype: PrintEvent, channel: &str, word: &[String]) {
             _ => PrintEvent::PRIVATE_MESSAGE_TO_DIALOG,
         };
 
-        print_event_to_channel(&ensure_tab(user), etype_dm, &[
-            word[0].as_str(), text, "",
-        ]);
+        ensure_tab(user);
+
+        if !print_event_to(user, etype_dm, &[word[0].as_str(), text, ""]) {
+            panic!("Failed to ensure Whisper Tab.");
+        }
     }
 }
 
@@ -604,20 +791,17 @@ fn host_notif(viewers: &str) -> String {
 
 
 pub fn hosttarget(msg: Message) -> Option<EatMode> {
-    let (target, viewers) = split_at_char(&msg.trail, ' ');
+    let (target, viewers) = split_at_char(msg.text(), ' ');
 
     if !target.is_empty() && target != "-" {
         let hashtarg: String = format!("#{}", target);
 
         //  Check whether we should try to follow the host.
         if PREF_FOLLOW_HOSTS.is(&true) {
-            let current = get_current_channel();
-            let focused = get_focused_channel();
-
             //  Check whether the source channel is currently focused.
-            if Some(current) == focused {
+            if is_focused() {
                 //  Join the target channel, if necessary.
-                if get_channel(NETWORK, &hashtarg).is_none() {
+                if !has_channel(&hashtarg) {
                     cmd!("JOIN {}", hashtarg);
                 }
 
@@ -630,13 +814,11 @@ pub fn hosttarget(msg: Message) -> Option<EatMode> {
             &hashtarg, &format!("https://twitch.tv/{}", target),
         ], TabColor::Event);
 
-        if let Some(channel) = get_channel(NETWORK, &hashtarg) {
-            print_event_to_channel(&channel, EVENT_REWARD, &[
-                "HOST",
-                &host_notif(viewers),
-                &msg.args[0],
-            ]);
-        }
+        print_event_to(&hashtarg, EVENT_REWARD, &[
+            "HOST",
+            &host_notif(viewers),
+            &msg.args[0],
+        ]);
     }
 
     Some(EatMode::Hexchat)
@@ -644,33 +826,126 @@ pub fn hosttarget(msg: Message) -> Option<EatMode> {
 
 
 pub fn clearmsg(msg: Message) -> Option<EatMode> {
-    alert_error(&format!(
-        "A message by <{}> is deleted: {}",
-        msg.get_tag("login")?, &msg.trail,
-    ));
-    Some(EatMode::Hexchat)
+    match TwitchEvent::try_from(&msg).ok()? {
+        TwitchEvent::ClearMsg { meta, login, target_msg_id, text } => {
+            //  If the message is still remembered, say when it was sent.
+            let sent: Option<String> = target_msg_id.and_then(|id| {
+                let channel = CHANNELS.ensure(meta.channel);
+                let entry: &HistoryEntry = channel.history.get(&id)?;
+
+                Some(entry.time_local())
+            });
+
+            match sent {
+                Some(time) => alert_error(format!(
+                    "A message by <{}> from {} is deleted: {}",
+                    login, time, text,
+                )),
+                None => alert_error(format!(
+                    "A message by <{}> is deleted: {}",
+                    login, text,
+                )),
+            }
+
+            Some(EatMode::Hexchat)
+        }
+        _ => None,
+    }
 }
 
 
 pub fn clearchat(msg: Message) -> Option<EatMode> {
-    if msg.trail.is_empty() {
-        alert_error("Chat history has been cleared.");
-    } else {
-        let mut text: String = String::with_capacity(128);
-
-        match msg.get_tag("ban-duration") {
-            Some(t) => write!(&mut text, "{} is timed out for {}s", &msg.trail, t).ok()?,
-            None => write!(&mut text, "{} is banned permanently", &msg.trail).ok()?,
-        };
+    let (meta, target, duration, reason) = match TwitchEvent::try_from(&msg).ok()? {
+        TwitchEvent::ClearChat { meta, target, duration, reason, .. } =>
+            (meta, target, duration, reason),
+        _ => return None,
+    };
+
+    match target {
+        None => alert_error("Chat history has been cleared."),
+        Some(target) => {
+            let mut text: String = String::with_capacity(128);
+
+            match duration {
+                Some(t) => write!(&mut text, "{} is timed out for {}s", target, t).ok()?,
+                None => write!(&mut text, "{} is banned permanently", target).ok()?,
+            };
 
-        if let Some(reason) = msg.get_tag("ban-reason") {
-            if !reason.is_empty() {
+            if let Some(reason) = reason {
                 write!(&mut text, ". Reason: {}", reason).ok()?;
             }
-        }
 
-        alert_error(&text);
+            alert_error(&text);
+
+            //  Show what the User said most recently, for context. The lines
+            //      are collected first, so that the Channel is not held while
+            //      they are printed.
+            let context: Vec<String> = CHANNELS.ensure(meta.channel).history
+                .last_from(&target, CLEAR_CONTEXT)
+                .into_iter()
+                .map(HistoryEntry::line)
+                .collect();
+
+            if !context.is_empty() {
+                alert_basic(format!(
+                    "Last {} message(s) from <{}>:",
+                    context.len(),
+                    target,
+                ));
+
+                for line in context {
+                    alert_basic(line);
+                }
+            }
+        }
     }
 
     Some(EatMode::Hexchat)
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::host::MockHost;
+    use super::*;
+
+    #[test]
+    fn test_usernotice_raid() {
+        let host = MockHost::new("#raided").install();
+        let msg: Message = r"@badges=;display-name=AsdfQwert;id=3d830f12-795c-447d-af3c-ea05e40fbddb;login=asdfqwert;msg-id=raid;msg-param-displayName=AsdfQwert;msg-param-login=asdfqwert;msg-param-viewerCount=42;room-id=1337;system-msg=42\sraiders\sfrom\sAsdfQwert\shave\sjoined!;tmi-sent-ts=1507246572675;user-id=123456789 :tmi.twitch.tv USERNOTICE #raided"
+            .parse().unwrap();
+
+        assert_eq!(usernotice(msg), Some(EatMode::Hexchat));
+        assert_eq!(host.take_lines(), [
+            "[Motd] A raid of 42 arrives from #asdfqwert",
+            "/GUI COLOR 1",
+        ]);
+
+        let stats = CHANNELS.get("#raided").unwrap().stats.clone();
+        assert_eq!((stats.raids, stats.raid_viewers), (1, 42));
+    }
+
+    #[test]
+    fn test_notice() {
+        let host = MockHost::new("#noticed").install();
+        let notice = |line: &str| notice(line.parse().unwrap());
+
+        CHANNELS.ensure("#noticed".into()).roomstate.followers = FollowMode::ForMinutes(10);
+        assert_eq!(
+            notice("@msg-id=msg_followersonly :tmi.twitch.tv NOTICE #noticed :This room is in 10 minutes followers-only mode."),
+            Some(EatMode::Hexchat),
+        );
+        assert_eq!(host.take_lines(), [
+            "[WhoIs Server Line] FOLLOWERS | Your message was not sent. Only Users who have followed this Channel for 10 minutes may chat.",
+            "/GUI COLOR 2",
+        ]);
+
+        //  Unknown IDs are left for HexChat, and remembered.
+        assert_eq!(
+            notice("@msg-id=msg_something_new :tmi.twitch.tv NOTICE #noticed :Something new."),
+            Some(EatMode::None),
+        );
+        assert!(host.take().is_empty());
+        assert!(NOTICES_UNKNOWN.get().contains("msg_something_new"));
+    }
+}
@@ -0,0 +1,158 @@
+//! Aggregation of gift subscriptions which are given out all at once.
+//!
+//! When a User gives out many random gift subscriptions, Twitch first sends one
+//!     `submysterygift` USERNOTICE, and then one `subgift` for every recipient.
+//!     All of them share the same `msg-param-origin-id`, which is used here to
+//!     gather the recipients together, so that they need not each be printed.
+
+use std::collections::VecDeque;
+use parking_lot::Mutex;
+
+
+/// The number of recent gift bombs to remember.
+const BOMBS_KEPT: usize = 8;
+
+/// The most recipients to make room for in advance. The count of a gift bomb
+///     comes from the server, and is not trusted to size an allocation.
+const RECIPIENTS_RESERVED: usize = 100;
+
+
+safe_static! {
+    static lazy BOMBS: Mutex<Bombs> = Default::default();
+}
+
+
+/// A set of gift subscriptions given out at once by one User.
+#[derive(Clone, Debug)]
+pub struct GiftBomb {
+    pub origin_id: String,
+    pub channel: String,
+    pub gifter: String,
+    /// The number of gifts announced by the `submysterygift`.
+    pub count: u32,
+    pub recipients: Vec<String>,
+}
+
+impl GiftBomb {
+    pub fn is_complete(&self) -> bool {
+        self.recipients.len() >= self.count as usize
+    }
+}
+
+
+/// The most recent gift bombs, oldest first.
+#[derive(Default)]
+struct Bombs { recent: VecDeque<GiftBomb> }
+
+impl Bombs {
+    fn start(&mut self, origin_id: String, channel: String, gifter: String, count: u32) {
+        if self.recent.len() >= BOMBS_KEPT {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back(GiftBomb {
+            origin_id,
+            channel,
+            gifter,
+            count,
+            recipients: Vec::with_capacity(RECIPIENTS_RESERVED.min(count as usize)),
+        });
+    }
+
+    fn add_recipient(&mut self, origin_id: &str, gifter: &str, recipient: &str) -> bool {
+        match self.recent.iter_mut().rev().find(|bomb| {
+            bomb.origin_id == origin_id
+                && bomb.gifter.eq_ignore_ascii_case(gifter)
+                && !bomb.is_complete()
+        }) {
+            Some(bomb) => {
+                bomb.recipients.push(recipient.to_owned());
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn find(&self, origin_id: Option<&str>, channel: &str) -> Option<&GiftBomb> {
+        let mut iter = self.recent.iter().rev();
+
+        match origin_id {
+            Some(id) => iter.find(|bomb| bomb.origin_id == id),
+            None => iter.find(|bomb| bomb.channel == channel),
+        }
+    }
+}
+
+
+/// Begin tracking a new gift bomb.
+pub fn start(origin_id: String, channel: String, gifter: String, count: u32) {
+    BOMBS.lock().start(origin_id, channel, gifter, count);
+}
+
+
+/// Add the recipient of a single gift to the bomb it belongs to. Returns
+///     `false` if no bomb matches the gift, in which case the gift should be
+///     shown by itself.
+///
+/// Input: `&str`, `&str`, `&str`
+/// Return: `bool`
+pub fn add_recipient(origin_id: &str, gifter: &str, recipient: &str) -> bool {
+    BOMBS.lock().add_recipient(origin_id, gifter, recipient)
+}
+
+
+/// Find a gift bomb by its origin ID or, if none is given, the most recent one
+///     in a Channel.
+///
+/// Input: `Option<&str>`, `&str`
+/// Return: `Option<GiftBomb>`
+pub fn find(origin_id: Option<&str>, channel: &str) -> Option<GiftBomb> {
+    BOMBS.lock().find(origin_id, channel).cloned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start(bombs: &mut Bombs, origin_id: &str, channel: &str, count: u32) {
+        bombs.start(origin_id.into(), channel.into(), String::from("Santa"), count);
+    }
+
+    #[test]
+    fn test_bombs() {
+        let mut bombs = Bombs::default();
+
+        //  A bogus count must not be trusted for an allocation.
+        start(&mut bombs, "huge", "#asdf", u32::MAX);
+        assert!(bombs.recent[0].recipients.capacity() <= RECIPIENTS_RESERVED);
+
+        start(&mut bombs, "abc", "#asdf", 2);
+        start(&mut bombs, "def", "#qwert", 5);
+
+        //  Gifts are matched by origin ID and gifter, until the bomb is full.
+        assert!(bombs.add_recipient("abc", "santa", "alice"));
+        assert!(!bombs.add_recipient("abc", "grinch", "bob"));
+        assert!(!bombs.add_recipient("xyz", "santa", "bob"));
+        assert!(bombs.add_recipient("abc", "Santa", "carol"));
+        assert!(!bombs.add_recipient("abc", "santa", "dave"));
+
+        let bomb: &GiftBomb = bombs.find(Some("abc"), "").unwrap();
+        assert!(bomb.is_complete());
+        assert_eq!(bomb.recipients, ["alice", "carol"]);
+
+        //  Without an ID, the most recent bomb in the Channel is found.
+        assert_eq!(bombs.find(None, "#asdf").unwrap().origin_id, "abc");
+        assert_eq!(bombs.find(None, "#qwert").unwrap().origin_id, "def");
+        assert!(bombs.find(None, "#zxcv").is_none());
+
+        //  Only the most recent bombs are kept.
+        for i in 0..BOMBS_KEPT {
+            start(&mut bombs, &format!("new{i}"), "#zxcv", 1);
+        }
+
+        assert_eq!(bombs.recent.len(), BOMBS_KEPT);
+        assert!(bombs.find(Some("abc"), "").is_none());
+        assert!(bombs.find(Some("new0"), "").is_some());
+    }
+}
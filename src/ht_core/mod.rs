@@ -1,19 +1,34 @@
 mod callbacks;
+mod capture;
+mod chatlog;
 mod events;
+mod gifts;
+mod outgoing;
 mod output;
+mod queue;
+mod ratelimit;
 mod storage;
 
-use std::{collections::HashSet, ops::Deref};
+use std::{collections::HashSet, ops::Deref, path::PathBuf, thread::JoinHandle, time::Duration};
 use chrono::{DateTime, Utc};
-use hexchat::{
-    ChannelRef,
-    EatMode,
-    get_channel_name,
-    get_network_name,
-    PrintEvent,
+use regex::Regex;
+use hexchat::{ChannelRef, EatMode, PrintEvent};
+use parking_lot::Mutex;
+
+use crate::{
+    helix::{self, HelixError},
+    host::{
+        get_channel_name,
+        get_config_dir,
+        get_network_name,
+        get_nickname,
+        print_plain,
+        strip_formatting,
+    },
+    irc::{Message, MessageRef, Signature},
+    NETWORK,
+    prefs::*,
 };
-
-use crate::{irc::Message, NETWORK, prefs::*};
 use output::{
     alert_basic,
     alert_error,
@@ -21,13 +36,28 @@ use output::{
     change_topic,
     CHANNELS,
     FAKE_MODE_NAME,
+    history::{HistoryEntry, HistoryFilter},
+    NOTICES_UNKNOWN,
+    stats::ExportFormat,
     print_with_irc,
     print_without_irc,
     TABCOLORS,
 };
+use callbacks::Notice;
+use ratelimit::Traffic;
 use storage::*;
 
 
+safe_static! {
+    /// A check of the Helix API token, running on another thread.
+    static lazy HELIX_CHECK: Mutex<Option<JoinHandle<HelixCheck>>> = Default::default();
+}
+
+
+/// The most characters of a malformed line to show in its error message.
+const PARSE_ERR_CHARS: usize = 200;
+
+
 /// Trim a slice of arguments from Hexchat into something workable. The initial
 ///     slice likely has only a few arguments that are not empty, with the rest
 ///     being placeholders left over from when the slice was first constructed
@@ -41,6 +71,16 @@ fn arg_trim(args: &[String]) -> &[String] {
 }
 
 
+/// Rebuild the text of a command from its words, as HexChat would give it in
+///     `word_eol`. Unlike [`arg_trim`], this does not stop at the first empty
+///     word, since HexChat gives one for each repeated space. Only the empty
+///     words that pad out the end are dropped.
+fn arg_text(args: &[String]) -> String {
+    let end: usize = args.iter().rposition(|arg| !arg.is_empty()).map_or(0, |i| i + 1);
+    args[..end].join(" ")
+}
+
+
 fn this_is_twitch() -> bool {
     match get_network_name() {
         Some(network) => network == NETWORK,
@@ -93,7 +133,21 @@ pub fn cb_mode(word: &[String], _dt: DateTime<Utc>) -> EatMode {
 
 pub fn cb_notice(word: &[String], _dt: DateTime<Utc>) -> EatMode {
     if this_is_twitch()  {
-        callbacks::run(&word[0])
+        let channel: String = get_channel_name();
+        let text: String = strip_formatting(&word[0]).unwrap_or_default();
+
+        //  The Tags of the NOTICE are only seen in the RAW LINE, so they must
+        //      be recovered from Storage.
+        let context: Option<Message> = word.get(1).and_then(|server| recover_context(
+            &Signature::new(Some(&channel), strip_formatting(server)),
+        ));
+        let msg_id: Option<String> = context.and_then(|msg| msg.get_tag("msg-id"));
+
+        callbacks::run(&Notice {
+            channel: &channel,
+            text: &text,
+            msg_id: msg_id.as_deref(),
+        })
     } else {
         EatMode::None
     }
@@ -111,19 +165,16 @@ pub fn cb_print(etype: PrintEvent, word: &[String], _dt: DateTime<Utc>) -> EatMo
 
     if this_is_twitch() {
         let channel: String = get_channel_name();
+        let sig: Signature = Signature::new(
+            Some(&channel),
+            strip_formatting(&word[0]),
+        );
 
         #[cfg(feature = "full-debug")]
-        hexchat::print_plain(&format!(
-            "{} < {}",
-            etype.get_id(),
-            crate::irc::Signature::new(
-                Some(&channel),
-                hexchat::strip_formatting(&word[0]),
-            ),
-        ));
+        print_plain(&format!("{} < {}", etype.get_id(), sig));
 
         //  Determine what should be done with this event.
-        match recover_message() {
+        match recover_message(&sig) {
             Action::Eat(eat) => eat,
             Action::ProcPrint if need_irc(etype) => EatMode::None,
             Action::ProcPrint => print_without_irc(&channel, etype, word),
@@ -135,47 +186,114 @@ pub fn cb_print(etype: PrintEvent, word: &[String], _dt: DateTime<Utc>) -> EatMo
 }
 
 
+/// Handle a line of plain text typed by the user, before HexChat sends it. In
+///     a Channel in slow mode, or when the rate limit has been reached, it may
+///     be held back and sent later instead.
+pub fn cb_say(arg_full: &[String]) -> EatMode {
+    //  Plain text has no command name, so every word is part of the message.
+    let text: String = arg_text(arg_full);
+    let channel: String = get_channel_name();
+
+    if !this_is_twitch() || text.is_empty() {
+        EatMode::None
+    } else if text.starts_with(".w ") {
+        let wait: Duration = ratelimit::wait(Traffic::Whisper, None);
+
+        if wait.is_zero() {
+            EatMode::None
+        } else {
+            alert_error(format!(
+                "Rate limit: Whisper NOT sent. Try again in {}s.",
+                wait.as_secs() + 1,
+            ));
+            EatMode::All
+        }
+    } else if !channel.starts_with('#') || text.starts_with('.') {
+        EatMode::None
+    } else if queue::submit(&channel, &text, None) {
+        EatMode::All
+    } else {
+        EatMode::None
+    }
+}
+
+
 /// Handle a Server Message, received by the Hook for "RAW LINE".
 pub fn cb_server(_word: &[String], _dt: DateTime<Utc>, raw: String) -> EatMode {
     if this_is_twitch() {
-        let msg: Message = raw.parse().expect("Failed to parse IRC Message");
+        //  Most lines are only looked at briefly, so they are not copied into
+        //      an owned Message unless a handler actually needs one.
+        let msg: MessageRef = match MessageRef::parse(&raw) {
+            Ok(msg) => msg,
+            Err(err) => {
+                capture_line(None, &raw);
+
+                //  A malformed line cannot be handled, but it should not be
+                //      allowed to take down the whole client either. Only the
+                //      start of it is shown, since it may be very long; The
+                //      whole line can be kept with HTCAPTURE.
+                let shown: String = match raw.char_indices().nth(PARSE_ERR_CHARS) {
+                    Some((cut, _)) => format!("{}…", &raw[..cut]),
+                    None => raw.clone(),
+                };
+
+                alert_error(format!("Failed to parse IRC Message ({}): {}", err, shown));
+                return EatMode::None;
+            }
+        };
 
         #[cfg(feature = "full-debug")]
-        hexchat::print_plain(&format!(
+        print_plain(&format!(
             "{} < {}",
             msg.command,
             msg.get_signature(),
         ));
 
-        let opt_eat: Option<EatMode> = match msg.command.as_str() {
+        capture_line(Some(&msg), &raw);
+
+        if PREF_CHAT_LOG.is(&true) {
+            chatlog::log(&msg, &raw);
+        }
+
+        let opt_eat: Option<EatMode> = match msg.command {
             //  Chat Messages.
             "PRIVMSG" => {
-                store_message(msg);
+                store_message(&msg, &raw);
                 Some(EatMode::None)
             }
-            "WHISPER" => events::whisper_recv(msg),
+            "WHISPER" => events::whisper_recv(msg.to_message()),
+            "NOTICE" => {
+                let eat: Option<EatMode> = events::notice(msg.to_message());
+
+                //  A NOTICE left for HexChat will reach `cb_notice`, which may
+                //      need its Tags.
+                if matches!(eat, Some(EatMode::None)) {
+                    store_message(&msg, &raw);
+                }
+
+                eat
+            }
 
             //  Status updates.
-            "HOSTTARGET" => events::hosttarget(msg),
-            "RECONNECT" => events::reconnect(msg),
-            "ROOMSTATE" => events::roomstate(msg),
-            "USERNOTICE" => events::usernotice(msg),
-            "USERSTATE" => events::userstate(msg),
+            "HOSTTARGET" => events::hosttarget(msg.to_message()),
+            "RECONNECT" => events::reconnect(msg.to_message()),
+            "ROOMSTATE" => events::roomstate(msg.to_message()),
+            "USERNOTICE" => events::usernotice(msg.to_message()),
+            "USERSTATE" => events::userstate(msg.to_message()),
 
             //  Moderator Actions.
-            "CLEARMSG" => events::clearmsg(msg),
-            "CLEARCHAT" => events::clearchat(msg),
+            "CLEARMSG" => events::clearmsg(msg.to_message()),
+            "CLEARCHAT" => events::clearchat(msg.to_message()),
 
             //  Suppress Hexchat spamming complaints that Twitch does not
             //      implement WHO and WHOIS Commands.
             //  TODO: If there is a way to prevent Hexchat from sending a WHO to
             //      every channel after connecting, that would be preferable to
             //      doing this.
-            "421" if msg.trail == "Unknown command" =>
-                match msg.args.get(1).map(String::as_str) {
-                    Some("WHO") | Some("WHOIS") => Some(EatMode::Hexchat),
-                    _ => Some(EatMode::None),
-                }
+            "421" if msg.text() == "Unknown command" => match msg.arg(1) {
+                Some("WHO") | Some("WHOIS") => Some(EatMode::Hexchat),
+                _ => Some(EatMode::None),
+            }
 
             //  Other.
             _ => Some(EatMode::None),
@@ -198,6 +316,72 @@ pub fn cb_server(_word: &[String], _dt: DateTime<Utc>, raw: String) -> EatMode {
 }
 
 
+fn capture_line(msg: Option<&MessageRef>, raw: &str) {
+    if let Some(err) = capture::tee(msg, raw) {
+        alert_error(format!("Capture stopped, failed to write line: {}", err));
+    }
+}
+
+
+pub fn cmd_htcapture(arg_full: &[String]) -> EatMode {
+    const USAGE: &str = "Usage: HTCAPTURE start [filter:<command|msg-id>] \
+        [redact] [<path>] | stop | status";
+
+    match arg_trim(&arg_full[1..]) {
+        [cmd, args @ ..] if cmd.eq_ignore_ascii_case("start") => {
+            let mut filters: Vec<String> = Vec::new();
+            let mut redact: bool = false;
+            let mut words: Vec<&str> = Vec::new();
+
+            for arg in args {
+                if let Some(filter) = arg.strip_prefix("filter:") {
+                    filters.extend(filter.split(',').map(String::from));
+                } else if arg.eq_ignore_ascii_case("redact") {
+                    redact = true;
+                } else {
+                    words.push(arg);
+                }
+            }
+
+            let path: PathBuf = if words.is_empty() {
+                let mut path: PathBuf = get_config_dir();
+                path.push(format!(
+                    "hextwitch-capture-{}.log",
+                    chrono::Local::now().format("%Y%m%d-%H%M%S"),
+                ));
+                path
+            } else {
+                PathBuf::from(words.join(" "))
+            };
+
+            let redaction = redact.then(|| capture::Redaction::new(
+                Some(get_nickname().as_str()),
+            ));
+
+            match capture::start(path.clone(), filters, redaction) {
+                Ok(()) => alert_basic(format!(
+                    "Capturing raw lines to {}.", path.display(),
+                )),
+                Err(e) => alert_error(format!(
+                    "Failed to open {}: {}", path.display(), e,
+                )),
+            }
+        }
+        [cmd] if cmd.eq_ignore_ascii_case("stop") => match capture::stop() {
+            Some(desc) => alert_basic(format!("Capture stopped: {}.", desc)),
+            None => alert_error("No capture is in progress."),
+        }
+        [cmd] if cmd.eq_ignore_ascii_case("status") => match capture::status() {
+            Some(desc) => alert_basic(format!("Capture in progress: {}.", desc)),
+            None => alert_basic("No capture is in progress."),
+        }
+        _ => alert_error(USAGE),
+    }
+
+    EatMode::All
+}
+
+
 pub fn cmd_pref_follow_hosts(_arg_full: &[String]) -> EatMode {
     match PREF_FOLLOW_HOSTS.toggle() {
         Ok(false) => alert_basic("Twitch hosts will NOT be followed to the target channel."),
@@ -209,6 +393,20 @@ pub fn cmd_pref_follow_hosts(_arg_full: &[String]) -> EatMode {
 }
 
 
+pub fn cmd_pref_chat_log(_arg_full: &[String]) -> EatMode {
+    match PREF_CHAT_LOG.toggle() {
+        Ok(false) => {
+            chatlog::close_all();
+            alert_basic("Twitch chat will NOT be logged as JSON Lines.")
+        }
+        Ok(true) => alert_basic("Twitch chat will now be logged as JSON Lines."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
 pub fn cmd_pref_announce(_arg_full: &[String]) -> EatMode {
     match PREF_ANNOUNCE.toggle() {
         Ok(false) => alert_basic("Announcements will NOT be shown with colored messages."),
@@ -220,6 +418,17 @@ pub fn cmd_pref_announce(_arg_full: &[String]) -> EatMode {
 }
 
 
+pub fn cmd_pref_hl_emotes(_arg_full: &[String]) -> EatMode {
+    match PREF_HL_EMOTES.toggle() {
+        Ok(false) => alert_basic("Emotes will NOT be shown in bold."),
+        Ok(true) => alert_basic("Emotes will now be shown in bold."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
 pub fn cmd_pref_debug(_arg_full: &[String]) -> EatMode {
     match PREF_DEBUG.toggle() {
         Ok(false) => alert_basic("Extra debug info will NOT be shown."),
@@ -231,6 +440,61 @@ pub fn cmd_pref_debug(_arg_full: &[String]) -> EatMode {
 }
 
 
+pub fn cmd_pref_gift_list(_arg_full: &[String]) -> EatMode {
+    match PREF_GIFT_LIST.toggle() {
+        Ok(false) => alert_basic("Recipients of gift subscriptions will NOT be listed automatically."),
+        Ok(true) => alert_basic("Recipients of gift subscriptions will now be listed automatically."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_pref_mark_first(_arg_full: &[String]) -> EatMode {
+    match PREF_MARK_FIRST.toggle() {
+        Ok(false) => alert_basic("First-time chatters will NOT be marked."),
+        Ok(true) => alert_basic("First-time chatters will now be marked."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_pref_mark_returning(_arg_full: &[String]) -> EatMode {
+    match PREF_MARK_RETURNING.toggle() {
+        Ok(false) => alert_basic("Returning chatters will NOT be marked."),
+        Ok(true) => alert_basic("Returning chatters will now be marked."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_pref_hl_new_chatters(_arg_full: &[String]) -> EatMode {
+    match PREF_HL_NEW_CHATTERS.toggle() {
+        Ok(false) => alert_basic("Marked chatters will NOT highlight the Tab."),
+        Ok(true) => alert_basic("Marked chatters will now highlight the Tab."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_pref_reply_mention(_arg_full: &[String]) -> EatMode {
+    match PREF_REPLY_MENTION.toggle() {
+        Ok(false) => alert_basic("Replies will NOT keep the @mention of their parent."),
+        Ok(true) => alert_basic("Replies will now keep the @mention of their parent."),
+        Err(..) => alert_error("FAILED to set Preference."),
+    }
+
+    EatMode::All
+}
+
+
 pub fn cmd_pref_whisper_here(_arg_full: &[String]) -> EatMode {
     match PREF_WHISPERS.toggle() {
         Ok(false) => alert_basic("Twitch whispers will ONLY be shown in their own Tab"),
@@ -247,31 +511,363 @@ pub fn cmd_automodes(_arg_full: &[String]) -> EatMode {
     cmd_ttv!("mods");
 
     #[cfg(feature = "fake-modes")]
-    callbacks::register(|s: &String| match s.strip_prefix(
-        "The moderators of this channel are: ",
-    ) {
-        Some(list) => {
-            let channel = get_channel_name();
+    callbacks::register(
+        callbacks::Matcher::msg_id(get_channel_name(), &["room_mods", "no_mods"]),
+        Duration::from_secs(5),
+        |notice: &Notice| {
+            let channel: &str = notice.channel;
 
             if let Some(owner) = channel.strip_prefix('#') {
-                output::fake_mode_op(&channel, owner, true);
+                output::fake_mode_op(channel, owner, true);
             }
 
-            for name in list.split(", ") {
-                output::fake_mode_op(&channel, name, true);
+            if let Some(list) = notice.text.strip_prefix(
+                "The moderators of this channel are: ",
+            ) {
+                for name in list.split(", ") {
+                    output::fake_mode_op(channel, name, true);
+                }
             }
 
             EatMode::All
+        },
+    );
+
+    EatMode::All
+}
+
+
+pub fn cmd_htsearch(arg_full: &[String]) -> EatMode {
+    /// The most results to show from a single search.
+    const MAX_RESULTS: usize = 50;
+
+    let mut args: &[String] = arg_trim(&arg_full[1..]);
+    let channel: String = match args {
+        [first, rest @ ..] if first.starts_with('#') => {
+            args = rest;
+            first.to_ascii_lowercase()
+        }
+        _ => get_channel_name(),
+    };
+
+    let mut filter: HistoryFilter = Default::default();
+    let mut words: Vec<&str> = Vec::new();
+
+    for arg in args {
+        if let Some(user) = arg.strip_prefix("user:") {
+            filter.user = Some(user.trim_start_matches('@').to_owned());
+        } else if let Some(badge) = arg.strip_prefix("badge:") {
+            filter.badge = Some(badge.to_owned());
+        } else {
+            words.push(arg);
+        }
+    }
+
+    if !words.is_empty() {
+        match Regex::new(&words.join(" ")) {
+            Ok(pattern) => filter.pattern = Some(pattern),
+            Err(err) => {
+                alert_error(format!("Invalid search pattern: {}", err));
+                return EatMode::All;
+            }
+        }
+    } else if filter.user.is_none() && filter.badge.is_none() {
+        alert_error("Usage: HTSEARCH [#channel] [user:<login>] [badge:<name>] <regex>");
+        return EatMode::All;
+    }
+
+    //  The lines are collected first, so that the Channel is not held while
+    //      they are printed.
+    let lines: Vec<String> = match CHANNELS.get(&channel) {
+        Some(data) => data.history.search(&filter, MAX_RESULTS)
+            .into_iter()
+            .map(HistoryEntry::line)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    if lines.is_empty() {
+        alert_basic(format!("No recent messages in {} match the search.", channel));
+    } else {
+        alert_basic(format!(
+            "{} recent message(s) in {} match the search:",
+            lines.len(),
+            channel,
+        ));
+
+        for line in lines {
+            alert_basic(line);
+        }
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_htgifts(arg_full: &[String]) -> EatMode {
+    //  Origin IDs may contain spaces, so all arguments are taken as one.
+    let origin_id: String = arg_trim(&arg_full[1..]).join(" ");
+    let origin_id: Option<&str> = Some(origin_id.as_str())
+        .filter(|id| !id.is_empty());
+
+    match gifts::find(origin_id, &get_channel_name()) {
+        Some(bomb) => {
+            alert_basic(format!(
+                "Gift subscriptions from <{}> ({}/{}): {}",
+                bomb.gifter,
+                bomb.recipients.len(),
+                bomb.count,
+                if bomb.recipients.is_empty() {
+                    String::from("(none yet)")
+                } else {
+                    bomb.recipients.join(", ")
+                },
+            ));
+
+            if !bomb.is_complete() && origin_id.is_some() {
+                //  NOTE: This is expected when called on a timer, if Twitch is
+                //      slow to send out the individual gifts.
+                alert_basic("Not all gifts have been received yet.");
+            }
+        }
+        None => alert_error("No recent gift subscriptions found."),
+    }
+
+    EatMode::All
+}
+
+
+/// The outcome of checking a Helix API token: The Client, whose Credentials may
+///     have been refreshed, and the details of its token.
+type HelixCheck = (helix::Client, Result<helix::TokenInfo, HelixError>);
+
+
+/// Validate the token of a Client, refreshing it first if it has expired. This
+///     does not use HexChat, so it may be run on another thread.
+fn helix_validate(mut client: helix::Client) -> HelixCheck {
+    let result = match client.validate() {
+        Err(HelixError::Unauthorized(_)) if client.credentials().refresh.is_some() => {
+            client.refresh().and_then(|()| client.validate())
+        }
+        result => result,
+    };
+
+    (client, result)
+}
+
+
+/// Save the token of a Client if it was refreshed, and report the outcome of
+///     checking it.
+fn helix_report((client, result): HelixCheck) {
+    if client.was_refreshed() && client.credentials().save().is_err() {
+        alert_error("Failed to save the refreshed Helix API token.");
+    }
+
+    match result {
+        Ok(info) => {
+            if info.client_id != client.credentials().client_id {
+                alert_error("Helix API: The token was issued to a different Client ID.");
+            }
+
+            let expiry: String = match info.expires_in {
+                Some(0) | None => String::from("does not expire"),
+                Some(sec) => format!("expires in {}h {}m", sec / 3600, sec % 3600 / 60),
+            };
+
+            alert_basic(format!(
+                "Helix API: Authorized as {}, with scopes [{}]. Token {}.",
+                info.login.as_deref().unwrap_or("an application"),
+                info.scopes.join(", "),
+                expiry,
+            ));
+        }
+        Err(err) => alert_error(format!("Helix API: Token is not valid: {}.", err)),
+    }
+}
+
+
+/// Begin validating the stored token for the Helix API on another thread, so
+///     that HexChat is not held up waiting for Twitch. The token is refreshed
+///     if it has expired. A `TIMER` is set to report the result once it is
+///     ready. Does nothing if no token has been set.
+pub fn helix_check_start() {
+    if let Ok(client) = helix::Client::from_prefs() {
+        *HELIX_CHECK.lock() = Some(std::thread::spawn(move || helix_validate(client)));
+        cmd!("TIMER 1 HTAUTH report");
+    }
+}
+
+
+/// Report the result of a check begun by [`helix_check_start`], if it has
+///     finished. If it has not, another `TIMER` is set.
+fn helix_check_finish() {
+    let handle: JoinHandle<HelixCheck> = match HELIX_CHECK.lock().take() {
+        Some(handle) => handle,
+        None => return,
+    };
+
+    if handle.is_finished() {
+        match handle.join() {
+            Ok(check) => helix_report(check),
+            Err(_) => alert_error("Helix API: Failed to check the token."),
+        }
+    } else {
+        *HELIX_CHECK.lock() = Some(handle);
+        cmd!("TIMER 1 HTAUTH report");
+    }
+}
+
+
+pub fn cmd_htauth(arg_full: &[String]) -> EatMode {
+    match arg_trim(&arg_full[1..]) {
+        [] => match helix::Credentials::load() {
+            Some(_) => helix_check_start(),
+            None => alert_basic("No Helix API token has been set."),
+        },
+        [arg] if arg.eq_ignore_ascii_case("clear") => match helix::Credentials::clear() {
+            Ok(()) => alert_basic("Helix API token cleared."),
+            Err(()) => alert_error("Failed to clear Helix API token."),
+        },
+        //  Run by the TIMER set when the token is checked.
+        [arg] if arg.eq_ignore_ascii_case("report") => helix_check_finish(),
+        //  Anything typed on the command line is kept in the input history, so
+        //      the token may be entered in a prompt instead.
+        [client_id] if client_id.bytes().all(|b| b.is_ascii_alphanumeric()) => cmd!(
+            "GETSTR \"\" \"HTAUTH {}\" \"Enter OAuth token, and refresh token if any\"",
+            client_id,
+        ),
+        [client_id, token, refresh @ ..] if refresh.len() <= 1 => {
+            let creds = helix::Credentials::new(
+                client_id,
+                token,
+                refresh.first().map(String::as_str),
+            );
+
+            match creds.save() {
+                Ok(()) => helix_check_start(),
+                Err(()) => alert_error("Failed to save Helix API token."),
+            }
+        }
+        _ => alert_error("Usage: HTAUTH [<client-id> [<token> [<refresh-token>]] | clear]"),
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_htqueue(arg_full: &[String]) -> EatMode {
+    match arg_trim(&arg_full[1..]) {
+        [] => {
+            let channel: String = get_channel_name();
+            let lines: Vec<String> = queue::waiting(&channel);
+
+            if lines.is_empty() {
+                alert_basic(format!("No messages are waiting in {}.", channel));
+            } else {
+                alert_basic(format!("Messages waiting in {}:", channel));
+
+                for (i, line) in lines.iter().enumerate() {
+                    alert_basic(format!("{}. {}", i + 1, line));
+                }
+            }
+        }
+        [arg] if arg.eq_ignore_ascii_case("clear") => {
+            let channel: String = get_channel_name();
+
+            match queue::clear(&channel) {
+                0 => alert_basic(format!("No messages are waiting in {}.", channel)),
+                n => alert_basic(format!("Cancelled {} message(s) in {}.", n, channel)),
+            }
         }
-        None => EatMode::None,
-    });
+        //  Run by the TIMER set when a message is queued.
+        [arg, channel] if arg.eq_ignore_ascii_case("send") => queue::release(channel),
+        _ => alert_error("Usage: HTQUEUE [clear]"),
+    }
+
+    EatMode::All
+}
+
+
+pub fn cmd_htstats(arg_full: &[String]) -> EatMode {
+    let mut args: &[String] = arg_trim(&arg_full[1..]);
+    let channel: String = match args {
+        [first, rest @ ..] if first.starts_with('#') => {
+            args = rest;
+            first.to_ascii_lowercase()
+        }
+        _ => get_channel_name(),
+    };
+
+    match args {
+        [] => {
+            let (since, lines) = match CHANNELS.get(&channel) {
+                Some(data) => (data.stats.since, data.stats.report()),
+                None => {
+                    alert_error(format!("No statistics for {}.", channel));
+                    return EatMode::All;
+                }
+            };
+
+            alert_basic(format!(
+                "Session statistics for {} since {}:",
+                channel,
+                since.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+            ));
+
+            for line in lines {
+                alert_basic(line);
+            }
+        }
+        [cmd] if cmd.eq_ignore_ascii_case("reset") => {
+            CHANNELS.ensure(channel.clone()).stats = Default::default();
+            alert_basic(format!("Session statistics for {} are reset.", channel));
+        }
+        [cmd, path @ ..] if cmd.eq_ignore_ascii_case("export") && !path.is_empty() => {
+            let path: String = path.join(" ");
+            let format = ExportFormat::from_path(&path);
+            let mut buf: Vec<u8> = Vec::new();
+
+            match CHANNELS.get(&channel) {
+                Some(data) => data.stats.export(format, &mut buf),
+                None => {
+                    alert_error(format!("No statistics for {}.", channel));
+                    return EatMode::All;
+                }
+            }.expect("Writing to a Vec cannot fail");
+
+            match std::fs::write(&path, buf) {
+                Ok(()) => alert_basic(format!(
+                    "Session statistics for {} exported to {}.", channel, path,
+                )),
+                Err(e) => alert_error(format!("Failed to write {}: {}", path, e)),
+            }
+        }
+        _ => alert_error("Usage: HTSTATS [#channel] [reset | export <path>]"),
+    }
 
     EatMode::All
 }
 
 
 pub fn cmd_htinfo(_arg_full: &[String]) -> EatMode {
-    hexchat::print_plain(crate::PLUGIN_INFO);
+    print_plain(crate::PLUGIN_INFO);
+    EatMode::All
+}
+
+
+pub fn cmd_emotes(_arg_full: &[String]) -> EatMode {
+    let channel = CHANNELS.current();
+
+    if channel.emotes.is_empty() {
+        alert_basic("No Emotes have been seen in this channel.");
+    } else {
+        alert_basic("Most used Emotes in this channel:");
+
+        for (name, count) in channel.emotes.top(10) {
+            alert_basic(format!("{}: {}", name, count));
+        }
+    }
+
     EatMode::All
 }
 
@@ -293,6 +889,47 @@ pub fn cmd_prediction(_arg_full: &[String]) -> EatMode {
 }
 
 
+pub fn cmd_reply(arg_full: &[String]) -> EatMode {
+    /// Message IDs are UUIDs, which can never be mistaken for a login name.
+    fn is_msg_id(target: &str) -> bool {
+        target.len() == 36
+            && target.bytes().all(|b| b.is_ascii_hexdigit() || b == b'-')
+    }
+
+    match arg_trim(&arg_full[1..]) {
+        _ if !this_is_twitch() => {}
+        [target, _, ..] => {
+            let channel: String = get_channel_name();
+            let parent: Option<String> = if is_msg_id(target) {
+                Some(target.to_ascii_lowercase())
+            } else {
+                let login: &str = target.trim_start_matches('@');
+
+                CHANNELS.current().history.last_from(login, 1).first()
+                    .and_then(|entry| entry.id.clone())
+            };
+
+            let text: String = arg_text(&arg_full[2..]);
+
+            match parent {
+                //  A reply waits in the queue like any other message, if it
+                //      has to.
+                Some(id) => if !queue::submit(&channel, &text, Some(&id)) {
+                    outgoing::say(&channel, &text, Some(&id));
+                },
+                None => alert_error(format!(
+                    "No recent message from {} to reply to.",
+                    target,
+                )),
+            }
+        }
+        _ => alert_error("Usage: REPLY <msg-id|nick> <text>"),
+    }
+
+    EatMode::All
+}
+
+
 pub fn cmd_reward(arg_full: &[String]) -> EatMode {
     match arg_trim(&arg_full[1..]) {
         [] => {
@@ -363,6 +1000,19 @@ pub fn cmd_unk_badges(_arg_full: &[String]) -> EatMode {
         }
     }
 
+    let unk: &HashSet<String> = &NOTICES_UNKNOWN.get();
+
+    if !unk.is_empty() {
+        alert_basic("The following NOTICE IDs do not have explanations:");
+
+        let mut vec: Vec<&String> = unk.iter().collect();
+        vec.sort_unstable();
+
+        for s in vec {
+            alert_basic(s);
+        }
+    }
+
     EatMode::All
 }
 
@@ -0,0 +1,128 @@
+//! Sending of IRC [`Message`]s, with Tags, to the Twitch server.
+//!
+//! HexChat has no way to attach Tags to a line sent with `SAY`, so tagged lines
+//!     are sent raw with `QUOTE` instead. This also means that HexChat does not
+//!     print them locally, so that is done here as well.
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+use hexchat::PrintEvent;
+use parking_lot::Mutex;
+use crate::{host::get_nickname, irc::{Message, MessageBuilder}};
+use super::{
+    ignore_next_print_event,
+    output::{CHANNELS, echo, TabColor, USERSTATE},
+    ratelimit::{self, Traffic},
+};
+
+
+/// The number of recently sent nonces to remember. Echoes of messages older
+///     than this will not be recognized.
+const NONCES_KEPT: usize = 32;
+
+
+safe_static! {
+    static lazy NONCES: Mutex<VecDeque<String>> = Default::default();
+}
+
+
+/// Generate a new value for a `client-nonce` Tag. It only needs to be unique
+///     among the messages sent by this client.
+///
+/// Return: `String`
+pub fn nonce() -> String {
+    static COUNT: AtomicU32 = AtomicU32::new(0);
+
+    let nanos: u128 = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos())
+        .unwrap_or_default();
+
+    format!("ht{:x}{:04x}", nanos, COUNT.fetch_add(1, Ordering::Relaxed) & 0xFFFF)
+}
+
+
+/// Check whether a Message is an echo of one that was sent by this plugin. If
+///     it is, its nonce is forgotten, so that it only matches once.
+///
+/// Input: `&Message`
+/// Return: `bool`
+pub fn is_own_echo(msg: &Message) -> bool {
+    match msg.get_tag("client-nonce") {
+        Some(nonce) => {
+            let mut nonces = NONCES.lock();
+
+            match nonces.iter().position(|sent| *sent == nonce) {
+                Some(idx) => {
+                    nonces.remove(idx);
+                    true
+                }
+                None => false,
+            }
+        }
+        None => false,
+    }
+}
+
+
+fn remember(nonce: String) {
+    let mut nonces = NONCES.lock();
+
+    if nonces.len() >= NONCES_KEPT {
+        nonces.pop_front();
+    }
+
+    nonces.push_back(nonce);
+}
+
+
+/// Send a Message to the server, exactly as it is written.
+pub fn send(msg: &Message) {
+    cmd!("QUOTE {}", msg);
+}
+
+
+/// Send a chat message to a Channel, tagged with a nonce, and print it locally
+///     as if HexChat had sent it. If a parent message ID is given, the message
+///     is sent as a reply to it.
+///
+/// Input: `&str`, `&str`, `Option<&str>`
+pub fn say(channel: &str, text: &str, parent: Option<&str>) {
+    let nonce: String = nonce();
+    let mut builder = MessageBuilder::new("PRIVMSG")
+        .tag("client-nonce", &nonce);
+
+    if let Some(id) = parent {
+        builder = builder.tag("reply-parent-msg-id", id);
+    }
+
+    let (etype, action_text);
+    let text: &str = match text.strip_prefix("/me ") {
+        Some(action) => {
+            etype = PrintEvent::YOUR_ACTION;
+            action_text = format!("\x01ACTION {}\x01", action);
+            builder = builder.arg(channel).trail(&action_text);
+            action
+        }
+        None => {
+            etype = PrintEvent::YOUR_MESSAGE;
+            builder = builder.arg(channel).trail(text);
+            text
+        }
+    };
+
+    send(&builder.build());
+    remember(nonce);
+    CHANNELS.ensure(channel.to_owned()).last_sent = Some(Instant::now());
+    ratelimit::record(Traffic::Chat);
+
+    ignore_next_print_event();
+    echo(etype, &[
+        get_nickname().as_str(), // Name
+        text, // Text
+        &USERSTATE.get(channel), // Mode
+        "", // "Identified text"
+    ], TabColor::Message);
+}
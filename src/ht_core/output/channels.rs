@@ -1,6 +1,6 @@
-use std::ops::{Deref, Neg};
-use hexchat::print_plain;
-use super::prediction::*;
+use std::{collections::HashMap, ops::{Deref, Neg}, time::Instant};
+use crate::host::print_plain;
+use super::{history::History, prediction::*, stats::SessionStats};
 
 
 #[derive(Clone, Copy, Eq, PartialEq)]
@@ -188,8 +188,41 @@ impl RoomState {
 }
 
 
+/// A count of how many times each Emote has been seen in a Channel, keyed by
+///     the text of the Emote.
+#[derive(Debug, Default)]
+pub struct EmoteUsage(HashMap<String, usize>);
+
+impl EmoteUsage {
+    pub fn add(&mut self, name: &str) {
+        match self.0.get_mut(name) {
+            Some(n) => *n += 1,
+            None => { self.0.insert(name.to_owned(), 1); }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Return the most used Emotes, most used first. Ties are sorted by name.
+    pub fn top(&self, count: usize) -> Vec<(&str, usize)> {
+        let mut vec: Vec<(&str, usize)> = self.0.iter()
+            .map(|(name, n)| (name.as_str(), *n))
+            .collect();
+
+        vec.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        vec.truncate(count);
+        vec
+    }
+}
+
+
 #[derive(Debug, Default)]
 pub struct ChannelData {
+    pub emotes: EmoteUsage,
+    pub history: History,
+    /// When the user last sent a chat message in this Channel.
+    pub last_sent: Option<Instant>,
     pub predictions: Predict,
     pub roomstate: RoomState,
+    pub stats: SessionStats,
 }
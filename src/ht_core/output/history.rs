@@ -0,0 +1,207 @@
+use std::collections::VecDeque;
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use crate::twitch::UserId;
+
+
+/// The number of messages to remember for each Channel.
+const HISTORY_LEN: usize = 256;
+
+
+/// A chat message, as it was seen in a Channel.
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    /// The ID of the message. Only missing if Twitch did not send one.
+    pub id: Option<String>,
+    /// Lowercase login name of the author.
+    pub login: String,
+    pub user_id: Option<UserId>,
+    pub time: DateTime<Utc>,
+    pub text: String,
+    /// The Badges of the author, as they were displayed.
+    pub badges: String,
+    /// The Badges of the author, as they were received in the `badges` Tag.
+    pub badge_tag: String,
+}
+
+impl HistoryEntry {
+    /// Format the time of the message for display, in the local timezone.
+    pub fn time_local(&self) -> String {
+        self.time.with_timezone(&Local).format("%H:%M:%S").to_string()
+    }
+
+    /// Format the message as a single line, with its time and author.
+    pub fn line(&self) -> String {
+        format!(
+            "[{}] {}<{}> {}",
+            self.time_local(),
+            self.badges,
+            self.login,
+            self.text,
+        )
+    }
+}
+
+
+/// Conditions that a [`HistoryEntry`] must meet to be found by a search. Every
+///     condition that is set must be met.
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    /// A login name or a numeric User ID.
+    pub user: Option<String>,
+    /// A Badge name, such as `subscriber`, or a Badge with its version, such
+    ///     as `subscriber/12`.
+    pub badge: Option<String>,
+    pub pattern: Option<Regex>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, entry: &HistoryEntry) -> bool {
+        let user_ok = match &self.user {
+            Some(user) => entry.login.eq_ignore_ascii_case(user)
+                || (entry.user_id.is_some() && entry.user_id == user.parse().ok()),
+            None => true,
+        };
+        let badge_ok = match &self.badge {
+            Some(badge) => entry.badge_tag.split(',').any(
+                |full| full == badge || full.split('/').next() == Some(badge)
+            ),
+            None => true,
+        };
+        let text_ok = match &self.pattern {
+            Some(pattern) => pattern.is_match(&entry.text),
+            None => true,
+        };
+
+        user_ok && badge_ok && text_ok
+    }
+}
+
+
+/// A bounded record of recent chat messages in a Channel. Once it is full, the
+///     oldest message is dropped for each new one.
+#[derive(Debug, Default)]
+pub struct History { entries: VecDeque<HistoryEntry> }
+
+impl History {
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.entries.len() >= HISTORY_LEN {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(entry);
+    }
+
+    /// Find a message by its ID.
+    pub fn get(&self, id: &str) -> Option<&HistoryEntry> {
+        self.entries.iter().rev().find(|entry| entry.id.as_deref() == Some(id))
+    }
+
+    /// Return the most recent messages that match a filter, oldest first.
+    pub fn search(&self, filter: &HistoryFilter, count: usize)
+        -> Vec<&HistoryEntry>
+    {
+        let mut vec: Vec<&HistoryEntry> = self.entries.iter().rev()
+            .filter(|entry| filter.matches(entry))
+            .take(count)
+            .collect();
+
+        vec.reverse();
+        vec
+    }
+
+    /// Return the most recent messages sent by a User, oldest first.
+    pub fn last_from(&self, login: &str, count: usize) -> Vec<&HistoryEntry> {
+        let mut vec: Vec<&HistoryEntry> = self.entries.iter().rev()
+            .filter(|entry| entry.login.eq_ignore_ascii_case(login))
+            .take(count)
+            .collect();
+
+        vec.reverse();
+        vec
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(n: usize, login: &str, badge_tag: &str, text: &str) -> HistoryEntry {
+        HistoryEntry {
+            id: Some(format!("id-{n}")),
+            login: login.to_owned(),
+            user_id: Some(1000 + n as UserId),
+            time: Utc::now(),
+            text: text.to_owned(),
+            badges: String::new(),
+            badge_tag: badge_tag.to_owned(),
+        }
+    }
+
+    fn ids(entries: Vec<&HistoryEntry>) -> Vec<&str> {
+        entries.into_iter().filter_map(|e| e.id.as_deref()).collect()
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::default();
+
+        for n in 0..HISTORY_LEN + 2 {
+            let login: &str = if n % 2 == 0 { "alice" } else { "bob" };
+            history.push(entry(n, login, "", &format!("message {n}")));
+        }
+
+        //  The oldest messages are dropped once the history is full.
+        assert_eq!(history.entries.len(), HISTORY_LEN);
+        assert!(history.get("id-0").is_none());
+        assert!(history.get("id-1").is_none());
+        assert_eq!(history.get("id-2").map(|e| e.text.as_str()), Some("message 2"));
+
+        //  The most recent messages are returned, oldest first.
+        let last: Vec<&str> = ids(history.last_from("ALICE", 3));
+        assert_eq!(last, ["id-252", "id-254", "id-256"]);
+        assert!(history.last_from("carol", 3).is_empty());
+
+        let filter = HistoryFilter {
+            pattern: Some(Regex::new(r"^message 25\d$").unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(ids(history.search(&filter, 2)), ["id-256", "id-257"]);
+        assert_eq!(history.search(&filter, 100).len(), 8);
+
+        //  A User may also be given by numeric ID.
+        let filter = HistoryFilter { user: Some(String::from("1257")), ..Default::default() };
+        assert_eq!(ids(history.search(&filter, 10)), ["id-257"]);
+    }
+
+    #[test]
+    fn test_filter() {
+        let mut history = History::default();
+
+        history.push(entry(0, "alice", "subscriber/12,bits/100", "hello there"));
+        history.push(entry(1, "alice", "bits/100", "hello again"));
+        history.push(entry(2, "bob", "subscriber/3", "hello from bob"));
+        history.push(entry(3, "alice", "subscriber/24", "goodbye"));
+        history.push(entry(4, "alice", "subscriber/24", "hello at last"));
+
+        //  Every condition that is set must be met at once.
+        let filter = HistoryFilter {
+            user: Some(String::from("Alice")),
+            badge: Some(String::from("subscriber")),
+            pattern: Some(Regex::new("^hello").unwrap()),
+        };
+        assert_eq!(ids(history.search(&filter, 10)), ["id-0", "id-4"]);
+
+        //  A Badge with a version must match exactly.
+        let filter = HistoryFilter {
+            badge: Some(String::from("subscriber/24")),
+            pattern: Some(Regex::new("hello").unwrap()),
+            ..filter
+        };
+        assert_eq!(ids(history.search(&filter, 10)), ["id-4"]);
+
+        //  No conditions at all match everything.
+        assert_eq!(history.search(&HistoryFilter::default(), 10).len(), 5);
+    }
+}
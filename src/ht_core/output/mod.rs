@@ -1,13 +1,27 @@
 pub(super) mod channels;
+pub(super) mod history;
 pub(super) mod prediction;
+pub(super) mod stats;
 mod printing;
 mod statics;
 mod tabs;
 
-use std::borrow::Cow;
-use hexchat::{EatMode, print_plain, PrintEvent};
-use crate::{irc::{Message, Prefix}, prefs::*};
-use super::{events, ignore_next_print_event};
+use std::{borrow::Cow, time::Instant};
+use hexchat::{EatMode, PrintEvent};
+use crate::{
+    host::{get_nickname, print_plain},
+    irc::{Message, Prefix},
+    prefs::*,
+    twitch::{Emotes, ReplyParent, timestamp},
+};
+use history::HistoryEntry;
+use stats::{StatEvent, StatKind};
+use super::{
+    events,
+    ignore_next_print_event,
+    outgoing,
+    ratelimit::{self, Traffic},
+};
 pub use printing::{
     alert_basic,
     alert_error,
@@ -23,16 +37,25 @@ pub use printing::{
     EVENT_NORMAL,
     EVENT_REWARD,
 };
-pub use statics::{BADGES_UNKNOWN, CHANNELS, TABCOLORS, USERSTATE};
+pub use statics::{BADGES_UNKNOWN, CHANNELS, NOTICES_UNKNOWN, TABCOLORS, USERSTATE};
 pub use tabs::TabColor;
 
 
 pub const FAKE_MODE_NAME: &str = "HexTwitch";
 
 
+/// The number of characters of a parent message to show in front of a reply.
+const REPLY_QUOTE_LEN: usize = 24;
+
+/// Marker put in front of the first message a User sends in a channel.
+const MARK_FIRST: &str = "\x0309\x02[FIRST]\x0F ";
+/// Marker put in front of a message from a returning chatter.
+const MARK_RETURNING: &str = "\x0310\x02[BACK]\x0F ";
+
+
 #[cfg(feature = "fake-joins")]
 pub(super) fn fake_join(channel: &str, user: &str) {
-    if user != hexchat::get_nickname() {
+    if user != get_nickname() {
         cmd!(
             "RECV :{user}!twitch.tv/{user} JOIN {channel}",
             channel = channel,
@@ -143,12 +166,12 @@ pub fn print_announcement(mut msg: Message) -> Option<EatMode> {
     if PREF_ANNOUNCE.is(&true) {
         //  If the announcement content was a `/me` invocation, it must be
         //      extracted from the `ACTION` frame and presented differently.
-        let (content, is_me) = match msg.trail.strip_prefix("\x01ACTION ") {
+        let (content, is_me) = match msg.text().strip_prefix("\x01ACTION ") {
             Some(action_x01) => match action_x01.strip_suffix('\x01') {
                 Some(action) => (action, true),
-                None => (msg.trail.as_str(), false),
+                None => (msg.text(), false),
             }
-            None => (msg.trail.as_str(), false),
+            None => (msg.text(), false),
         };
 
         print_plain(&format!(
@@ -167,6 +190,39 @@ pub fn print_announcement(mut msg: Message) -> Option<EatMode> {
 }
 
 
+/// The marker to put in front of a message from a new or returning chatter, or
+///     an empty string if it should not have one.
+fn chatter_marker(msg: &Message) -> &'static str {
+    if msg.get_tag("first-msg").as_deref() == Some("1")
+        && PREF_MARK_FIRST.is(&true)
+    {
+        MARK_FIRST
+    } else if msg.get_tag("returning-chatter").as_deref() == Some("1")
+        && PREF_MARK_RETURNING.is(&true)
+    {
+        MARK_RETURNING
+    } else {
+        ""
+    }
+}
+
+
+/// Add a chat message to the history of its Channel.
+fn remember(channel: &str, msg: &Message, text: &str, badges: &Badges) {
+    CHANNELS.ensure(channel.to_owned()).history.push(HistoryEntry {
+        id: msg.get_tag("id"),
+        login: msg.author().to_ascii_lowercase(),
+        user_id: msg.get_tag("user-id").and_then(|id| id.parse().ok()),
+        time: msg.get_tag("tmi-sent-ts")
+            .and_then(|ts| timestamp(&ts))
+            .unwrap_or_else(chrono::Utc::now),
+        text: text.to_owned(),
+        badges: badges.as_str().to_owned(),
+        badge_tag: msg.get_tag("badges").unwrap_or_default(),
+    });
+}
+
+
 /// Message comes from Server. IRC Representation available.
 pub fn print_with_irc(
     channel: &str,
@@ -175,15 +231,41 @@ pub fn print_with_irc(
     msg: Message,
 ) -> EatMode {
     let author: &str = msg.author();
+    let marker: &str = chatter_marker(&msg);
+    //  Messages from new and returning chatters may highlight the tab.
+    let marker_color: TabColor = if !marker.is_empty() && PREF_HL_NEW_CHATTERS.is(&true) {
+        TabColor::Highlight
+    } else {
+        TabColor::Message
+    };
 
     if msg.has_tags() {
         if let Some(bits) = msg.get_tag("bits") {
-            if let Ok(n) = bits.parse::<usize>() {
+            //  A value too large to be real is ignored.
+            if let Ok(n) = bits.parse::<u32>() {
                 events::cheer(author, n);
+
+                CHANNELS.ensure(channel.to_owned()).stats.record(StatEvent {
+                    kind: StatKind::Bits,
+                    time: msg.get_tag("tmi-sent-ts")
+                        .and_then(|ts| timestamp(&ts))
+                        .unwrap_or_else(chrono::Utc::now),
+                    user: &author.to_ascii_lowercase(),
+                    amount: n,
+                    plan: None,
+                });
             }
         }
 
-        if let Some(eat) = events::reward(word, &msg) {
+        if let Some(eat) = events::reward(word, &msg, marker, marker_color) {
+            //  Rewards and highlighted messages are still chat, and must be
+            //      found by searches and moderation context like any other.
+            let badges: Badges = badge_parse(
+                msg.get_tag("badges").unwrap_or_default(),
+                msg.get_tag("badge-info").unwrap_or_default(),
+            );
+
+            remember(channel, &msg, &word[1], &badges);
             return eat;
         }
     }
@@ -223,6 +305,25 @@ pub fn print_with_irc(
         | PrintEvent::CHANNEL_MSG_HILIGHT
         | PrintEvent::CHANNEL_ACTION_HILIGHT
         => {
+            //  Messages sent by the plugin itself have already been printed.
+            if outgoing::is_own_echo(&msg) {
+                return EatMode::All;
+            }
+
+            //  A reply to one of our own messages is a highlight, even if the
+            //      mention of our name is not kept.
+            let reply: Option<ReplyParent> = ReplyParent::from_message(&msg);
+            let to_me: bool = matches!(
+                &reply, Some(parent) if parent.is_to(&get_nickname()),
+            );
+            let etype: PrintEvent = match etype {
+                PrintEvent::CHANNEL_MESSAGE if to_me
+                => PrintEvent::CHANNEL_MSG_HILIGHT,
+                PrintEvent::CHANNEL_ACTION if to_me
+                => PrintEvent::CHANNEL_ACTION_HILIGHT,
+                other => other,
+            };
+
             let badges: Badges = badge_parse(
                 msg.get_tag("badges").unwrap_or_default(),
                 msg.get_tag("badge-info").unwrap_or_default(),
@@ -231,7 +332,7 @@ pub fn print_with_irc(
                 PrintEvent::CHANNEL_ACTION_HILIGHT
                 | PrintEvent::CHANNEL_MSG_HILIGHT
                 => TabColor::Highlight,
-                _ => TabColor::Message,
+                _ => marker_color,
             };
 
             #[cfg(any(
@@ -253,6 +354,49 @@ pub fn print_with_irc(
                 // }
             }
 
+            let emotes: Emotes = msg.get_tag("emotes")
+                .and_then(|tag| tag.parse().ok())
+                .unwrap_or_default();
+            let text_owned: String;
+            let text: &str = if emotes.is_empty() {
+                &word[1]
+            } else {
+                let usage = &mut CHANNELS.ensure(channel.to_owned()).emotes;
+
+                for (_, _, emote) in emotes.locate(&word[1]) {
+                    usage.add(emote);
+                }
+
+                if PREF_HL_EMOTES.is(&true) {
+                    text_owned = emotes.highlight(&word[1], "\x02", "\x02");
+                    &text_owned
+                } else {
+                    &word[1]
+                }
+            };
+
+            let reply_owned: String;
+            let text: &str = match &reply {
+                Some(parent) => {
+                    reply_owned = format!(
+                        "{marker}\x0314{quote}\x0F  {text}",
+                        marker = marker,
+                        quote = parent.quote(REPLY_QUOTE_LEN),
+                        text = if PREF_REPLY_MENTION.is(&true) {
+                            text
+                        } else {
+                            parent.strip_mention(text)
+                        },
+                    );
+                    &reply_owned
+                }
+                None if !marker.is_empty() => {
+                    reply_owned = format!("{}{}", marker, text);
+                    &reply_owned
+                }
+                None => text,
+            };
+
             let name_owned: String;
             let name: &str = match msg.get_tag("display-name").as_ref()
                 .map(|s| s.trim())
@@ -267,11 +411,13 @@ pub fn print_with_irc(
             ignore_next_print_event();
             echo(etype, &[
                 name, // Name
-                word[1].as_str(), // Text
+                text, // Text
                 badges.as_str(), // Mode
                 word[3].as_str(), // "Identified text"
             ], color);
 
+            remember(channel, &msg, &word[1], &badges);
+
             badges.update_prediction(&channel);
 
             EatMode::All
@@ -306,6 +452,8 @@ pub fn print_without_irc(channel: &str, etype: PrintEvent, word: &[String]) -> E
     } else {
         //  User has spoken in a normal Channel, but has not yet been given
         //      Badges. Add the Badges from the User State and re-emit.
+        CHANNELS.ensure(channel.to_owned()).last_sent = Some(Instant::now());
+        ratelimit::record(Traffic::Chat);
         ignore_next_print_event();
         echo(etype, &[
             word[0].as_str(), // Name
@@ -327,3 +475,53 @@ fn twitch_command(line: &str) -> Option<&str> {
         _ => None,
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::host::MockHost;
+    use super::*;
+
+    #[test]
+    fn test_reward_history() {
+        const CHANNEL: &str = "#rewarded";
+        let host = MockHost::new(CHANNEL).install();
+        let msg: Message = r"@badges=subscriber/12;display-name=AsdfQwert;id=8f0a5c3e-1b2d-4e6f-9a7b-0c1d2e3f4a5b;msg-id=highlighted-message;tmi-sent-ts=1642696567751;user-id=123456789 :asdfqwert!asdfqwert@asdfqwert.tmi.twitch.tv PRIVMSG #rewarded :look at me"
+            .parse().unwrap();
+        let word: Vec<String> = ["asdfqwert", "look at me", "", ""].map(String::from).to_vec();
+
+        print_with_irc(CHANNEL, PrintEvent::CHANNEL_MESSAGE, &word, msg);
+        assert!(host.take_lines().iter().any(|line| line.ends_with("asdfqwert | look at me")));
+
+        //  A highlighted message is printed differently, but still remembered.
+        let data = CHANNELS.get(CHANNEL).unwrap();
+        let entry = data.history.get("8f0a5c3e-1b2d-4e6f-9a7b-0c1d2e3f4a5b").unwrap();
+        assert_eq!(entry.login, "asdfqwert");
+        assert_eq!(entry.text, "look at me");
+        assert_eq!(entry.badge_tag, "subscriber/12");
+
+        //  So it can also be found by a search.
+        let filter = history::HistoryFilter {
+            user: Some(String::from("asdfqwert")),
+            badge: Some(String::from("subscriber")),
+            pattern: Some(regex::Regex::new("look").unwrap()),
+        };
+        assert_eq!(data.history.search(&filter, 10).len(), 1);
+    }
+
+    #[test]
+    fn test_reward_marker() {
+        const CHANNEL: &str = "#newcomers";
+        let host = MockHost::new(CHANNEL).install();
+        let msg: Message = r"@badges=;custom-reward-id=0f0e0d0c-0b0a-0908-0706-050403020100;first-msg=1;id=1f0a5c3e-1b2d-4e6f-9a7b-0c1d2e3f4a5b;user-id=987654321 :qwert!qwert@qwert.tmi.twitch.tv PRIVMSG #newcomers :hi all"
+            .parse().unwrap();
+        let word: Vec<String> = ["qwert", "hi all", "", ""].map(String::from).to_vec();
+
+        //  A first message is marked, even when it redeems a Reward.
+        print_with_irc(CHANNEL, PrintEvent::CHANNEL_MESSAGE, &word, msg);
+
+        let lines: Vec<String> = host.take_lines();
+        let expected: String = format!("qwert: | {}hi all", MARK_FIRST);
+        assert!(lines.iter().any(|line| line.ends_with(&expected)), "{:?}", lines);
+    }
+}
@@ -314,7 +314,7 @@ impl Predict {
             Some(s) if label == s => false,
             _ => {
                 if badge.value() == 0 && PREF_DEBUG.is(&true) {
-                    hexchat::print_plain(&format!(
+                    crate::host::print_plain(&format!(
                         "Zero-value Prediction badge received: {}",
                         badge,
                     ));
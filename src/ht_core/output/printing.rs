@@ -1,7 +1,10 @@
 use std::collections::hash_map::{Entry, HashMap};
 use cached::proc_macro::cached;
-use hexchat::{print_event, PrintEvent};
-use crate::irc::split_at_char;
+use hexchat::PrintEvent;
+use crate::{
+    host::{get_topic, has_channel, print_event, print_event_to},
+    irc::split_at_char,
+};
 use super::{
     prediction::PredictionBadge,
     statics::{BADGES_UNKNOWN, CHANNELS, TABCOLORS},
@@ -52,8 +55,8 @@ pub fn alert_sub_upgrade(message: impl AsRef<str>) {
 
 
 pub fn change_topic(channel: &str, new: &str) {
-    if let Some(cref) = hexchat::get_channel(crate::NETWORK, channel) {
-        let topic_old = hexchat::get_topic(&cref);
+    if has_channel(channel) {
+        let topic_old = get_topic(channel);
         let print_old: Option<&String>;
         let apply_new: bool;
 
@@ -78,8 +81,8 @@ pub fn change_topic(channel: &str, new: &str) {
         };
 
         if let Some(old) = print_old {
-            hexchat::print_event_to_channel(
-                &cref,
+            print_event_to(
+                channel,
                 PrintEvent::TOPIC,
                 &[channel, old],
                 // TabColor::None,
@@ -93,24 +96,6 @@ pub fn change_topic(channel: &str, new: &str) {
 }
 
 
-/*pub fn print_topic(channel: &str) -> Option<()> {
-    if let Some(cref) = hexchat::get_channel(crate::NETWORK, channel) {
-        let topic: String = hexchat::get_topic(&cref)?;
-
-        if !topic.is_empty() {
-            hexchat::print_event_to_channel(
-                &cref,
-                PrintEvent::TOPIC,
-                &[channel, &topic],
-                // TabColor::None,
-            );
-        }
-    }
-
-    Some(())
-}*/
-
-
 /// BITS: Badge characters for Bits. If a User has a Bits Badge, the User is
 ///     given the `char` corresponding to the last value found here which is
 ///     LESS THAN OR EQUAL TO the Rank of the Badge.
@@ -346,7 +331,6 @@ impl Badges {
         self.output.is_none()
     }
 
-    #[allow(dead_code)]
     pub fn is_op(&self) -> bool {
         match &self.output {
             Some(s) => s.contains(ICONS_OP),
@@ -447,6 +431,15 @@ impl States {
         self.inner.contains_key(channel)
     }
 
+    /// Check whether the User is a moderator or broadcaster in a given
+    ///     Channel.
+    ///
+    /// Input: `&str`
+    /// Return: `bool`
+    pub fn is_op(&self, channel: &str) -> bool {
+        self.inner.get(channel).is_some_and(Badges::is_op)
+    }
+
     /// Set the Badges for the User in a given Channel. This is mostly just a
     ///     guarded passthrough to the internal HashMap, but with one
     ///     significant difference: If the current value for the given Channel
@@ -14,6 +14,7 @@ use super::{
 safe_static! {
     pub static lazy BADGES_UNKNOWN: BadgesUnknown = Default::default();
     pub static lazy CHANNELS: Channels = Default::default();
+    pub static lazy NOTICES_UNKNOWN: NoticesUnknown = Default::default();
     pub static lazy TABCOLORS: TabColors = Default::default();
     pub static lazy USERSTATE: UserState = Default::default();
 }
@@ -38,12 +39,34 @@ impl BadgesUnknown {
 }
 
 
+/// IDs of NOTICEs that the plugin does not know how to explain.
+#[derive(Default)]
+pub struct NoticesUnknown(RwLock<HashSet<String>>);
+
+impl NoticesUnknown {
+    pub fn add(&self, msg_id: impl Into<String>) {
+        self.0.write().insert(msg_id.into());
+    }
+
+    pub fn get<'s>(&'s self) -> impl Deref<Target=HashSet<String>> + 's {
+        self.0.read()
+    }
+}
+
+
 #[derive(Default)]
 pub struct Channels(RwLock<HashMap<String, ChannelData>>);
 
 impl Channels {
     pub fn current(&self) -> impl DerefMut<Target=ChannelData> + '_ {
-        self.ensure(hexchat::get_channel_name())
+        self.ensure(crate::host::get_channel_name())
+    }
+
+    /// Get the data for a Channel, but only if it has already been created.
+    pub fn get<'s>(&'s self, channel: &str)
+        -> Option<impl Deref<Target=ChannelData> + 's>
+    {
+        RwLockReadGuard::try_map(self.0.read(), |map| map.get(channel)).ok()
     }
 
     pub fn ensure<'s>(&'s self, channel: String)
@@ -89,6 +112,10 @@ impl UserState {
         self.0.read().has(channel)
     }
 
+    pub fn is_op(&self, channel: &str) -> bool {
+        self.0.read().is_op(channel)
+    }
+
     pub fn set<'s>(&'s self, channel: String, bstr: String, meta: String)
         -> Option<impl Deref<Target=Badges> + 's>
     {
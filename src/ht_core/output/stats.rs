@@ -0,0 +1,335 @@
+use std::io::{self, Write};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::json;
+use crate::twitch::SubPlan;
+
+
+/// List prices, in US cents, of subscriptions at each tier. Prime subscriptions
+///     are valued the same as Tier 1.
+const SUB_PRICES: [u64; 3] = [499, 999, 2499];
+/// Price, in US cents, of a single Bit.
+const BIT_PRICE: u64 = 1;
+
+
+/// A kind of event that is counted in the session statistics.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StatKind {
+    Sub,
+    Resub,
+    Gift,
+    Bits,
+    Raid,
+}
+
+impl StatKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sub => "sub",
+            Self::Resub => "resub",
+            Self::Gift => "gift",
+            Self::Bits => "bits",
+            Self::Raid => "raid",
+        }
+    }
+}
+
+
+/// A single event to be counted.
+#[derive(Clone, Copy, Debug)]
+pub struct StatEvent<'a> {
+    pub kind: StatKind,
+    /// Time of the event, from the `tmi-sent-ts` Tag where possible.
+    pub time: DateTime<Utc>,
+    /// Login name of the User responsible. For a gift, this is the sender.
+    pub user: &'a str,
+    /// Bits cheered, or viewers brought by a raid. For subscriptions, this is
+    ///     always one.
+    pub amount: u32,
+    pub plan: Option<&'a SubPlan>,
+}
+
+
+/// A single event, as it is kept for export.
+#[derive(Clone, Debug)]
+pub struct LedgerEntry {
+    pub time: DateTime<Utc>,
+    pub kind: StatKind,
+    pub user: String,
+    pub amount: u32,
+    pub plan: Option<SubPlan>,
+}
+
+impl LedgerEntry {
+    fn time_str(&self) -> String {
+        self.time.to_rfc3339_opts(SecondsFormat::Millis, true)
+    }
+
+    /// Describe the plan of a subscription as `Prime`, or as the number of its
+    ///     tier. Plans not known to the plugin are written as they were sent.
+    fn tier_str(&self) -> &str {
+        match &self.plan {
+            Some(SubPlan::Prime) => "Prime",
+            Some(SubPlan::Tier1) => "1",
+            Some(SubPlan::Tier2) => "2",
+            Some(SubPlan::Tier3) => "3",
+            Some(SubPlan::Other(other)) => other,
+            None => "",
+        }
+    }
+}
+
+
+/// A format in which the ledger can be exported.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExportFormat {
+    Csv,
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// Choose a format by the extension of a file path. Anything other than
+    ///     `.json` or `.jsonl` is written as CSV.
+    pub fn from_path(path: &str) -> Self {
+        let lower: String = path.to_ascii_lowercase();
+
+        if lower.ends_with(".jsonl") || lower.ends_with(".json") {
+            Self::JsonLines
+        } else {
+            Self::Csv
+        }
+    }
+}
+
+
+/// Quote a field for CSV, if it needs to be.
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+
+/// Counts of the events seen in a Channel since the session began.
+#[derive(Clone, Debug)]
+pub struct SessionStats {
+    pub since: DateTime<Utc>,
+    /// Paid subscriptions and resubscriptions, by tier.
+    pub subs: [u32; 3],
+    pub prime: u32,
+    /// Gift subscriptions, by tier.
+    pub gifts: [u32; 3],
+    pub bits: u64,
+    pub raids: u32,
+    pub raid_viewers: u64,
+    /// Every event counted, in the order they were seen.
+    pub ledger: Vec<LedgerEntry>,
+}
+
+impl Default for SessionStats {
+    fn default() -> Self {
+        Self {
+            since: Utc::now(),
+            subs: [0; 3],
+            prime: 0,
+            gifts: [0; 3],
+            bits: 0,
+            raids: 0,
+            raid_viewers: 0,
+            ledger: Vec::new(),
+        }
+    }
+}
+
+impl SessionStats {
+    pub fn record(&mut self, event: StatEvent) {
+        //  A plan not known to the plugin is counted at Tier 1, so that it is
+        //      at least not missing from the totals.
+        let tier: usize = event.plan.and_then(SubPlan::tier).unwrap_or(1) as usize;
+
+        match event.kind {
+            StatKind::Sub | StatKind::Resub => match event.plan {
+                Some(SubPlan::Prime) => self.prime += 1,
+                _ => self.subs[tier - 1] += 1,
+            }
+            StatKind::Gift => self.gifts[tier - 1] += 1,
+            StatKind::Bits => self.bits += event.amount as u64,
+            StatKind::Raid => {
+                self.raids += 1;
+                self.raid_viewers += event.amount as u64;
+            }
+        }
+
+        self.ledger.push(LedgerEntry {
+            time: event.time,
+            kind: event.kind,
+            user: event.user.to_owned(),
+            amount: event.amount,
+            plan: event.plan.cloned(),
+        });
+    }
+
+    pub fn total_subs(&self) -> u32 {
+        self.subs.iter().sum::<u32>() + self.prime
+    }
+
+    pub fn total_gifts(&self) -> u32 {
+        self.gifts.iter().sum()
+    }
+
+    /// Estimate the value of the session in US dollars, at list prices. This is
+    ///     what viewers paid, not what the Channel receives.
+    pub fn estimate(&self) -> f64 {
+        let subs: u64 = self.subs.iter()
+            .zip(self.gifts.iter())
+            .zip(SUB_PRICES.iter())
+            .map(|((&subs, &gifts), &price)| (subs + gifts) as u64 * price)
+            .sum();
+        let prime: u64 = self.prime as u64 * SUB_PRICES[0];
+        let bits: u64 = self.bits * BIT_PRICE;
+
+        (subs + prime + bits) as f64 * 0.01
+    }
+
+    /// Describe the statistics in a few lines of text.
+    pub fn report(&self) -> Vec<String> {
+        vec![
+            format!(
+                "Subs: {} (T1: {}, T2: {}, T3: {}, Prime: {})",
+                self.total_subs(),
+                self.subs[0], self.subs[1], self.subs[2], self.prime,
+            ),
+            format!(
+                "Gifts: {} (T1: {}, T2: {}, T3: {})",
+                self.total_gifts(),
+                self.gifts[0], self.gifts[1], self.gifts[2],
+            ),
+            format!("Bits: {}", self.bits),
+            format!("Raids: {} ({} viewers)", self.raids, self.raid_viewers),
+            format!("Estimated value: ${:.2}", self.estimate()),
+        ]
+    }
+
+    /// Write the ledger of events, with a header row, as CSV.
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "time,type,user,amount,tier")?;
+
+        for entry in &self.ledger {
+            writeln!(
+                out, "{},{},{},{},{}",
+                entry.time_str(),
+                entry.kind.as_str(),
+                csv_field(&entry.user),
+                entry.amount,
+                csv_field(entry.tier_str()),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the ledger of events as JSON Lines, one object per event.
+    pub fn write_jsonl(&self, mut out: impl Write) -> io::Result<()> {
+        for entry in &self.ledger {
+            let object = json!({
+                "time": entry.time_str(),
+                "type": entry.kind.as_str(),
+                "user": entry.user,
+                "amount": entry.amount,
+                "tier": entry.plan.as_ref().map(|_| entry.tier_str()),
+            });
+
+            writeln!(out, "{}", object)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn export(&self, format: ExportFormat, out: impl Write) -> io::Result<()> {
+        match format {
+            ExportFormat::Csv => self.write_csv(out),
+            ExportFormat::JsonLines => self.write_jsonl(out),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::twitch::timestamp;
+    use super::*;
+
+    #[test]
+    fn test_estimate() {
+        let mut stats = SessionStats::default();
+        let event = |kind, amount, plan| StatEvent {
+            kind,
+            time: Utc::now(),
+            user: "asdfqwert",
+            amount,
+            plan,
+        };
+
+        stats.record(event(StatKind::Sub, 1, Some(&SubPlan::Tier1)));
+        stats.record(event(StatKind::Resub, 1, Some(&SubPlan::Prime)));
+        stats.record(event(StatKind::Resub, 1, Some(&SubPlan::Tier3)));
+        stats.record(event(StatKind::Gift, 1, Some(&SubPlan::Tier2)));
+        stats.record(event(StatKind::Gift, 1, None));
+        stats.record(event(StatKind::Bits, 250, None));
+        stats.record(event(StatKind::Raid, 42, None));
+
+        assert_eq!(stats.subs, [1, 0, 1]);
+        assert_eq!(stats.prime, 1);
+        assert_eq!(stats.gifts, [1, 1, 0]);
+        assert_eq!(stats.total_subs(), 3);
+        assert_eq!(stats.total_gifts(), 2);
+        assert_eq!((stats.raids, stats.raid_viewers), (1, 42));
+
+        //  4.99 * 3 + 9.99 + 24.99 + 2.50
+        assert_eq!(format!("{:.2}", stats.estimate()), "52.45");
+    }
+
+    #[test]
+    fn test_export() {
+        let mut stats = SessionStats::default();
+        let time = timestamp("1507246572675").unwrap();
+
+        stats.record(StatEvent {
+            kind: StatKind::Resub,
+            time,
+            user: "asdfqwert",
+            amount: 1,
+            plan: Some(&SubPlan::Prime),
+        });
+        stats.record(StatEvent {
+            kind: StatKind::Bits,
+            time,
+            user: "weird\"name,",
+            amount: 100,
+            plan: None,
+        });
+
+        let mut csv = Vec::new();
+        stats.export(ExportFormat::Csv, &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "time,type,user,amount,tier\n\
+            2017-10-05T23:36:12.675Z,resub,asdfqwert,1,Prime\n\
+            2017-10-05T23:36:12.675Z,bits,\"weird\"\"name,\",100,\n",
+        );
+
+        let mut jsonl = Vec::new();
+        stats.export(ExportFormat::JsonLines, &mut jsonl).unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap(),
+            r#"{"time":"2017-10-05T23:36:12.675Z","type":"resub","user":"asdfqwert","amount":1,"tier":"Prime"}"#.to_owned()
+                + "\n"
+                + r#"{"time":"2017-10-05T23:36:12.675Z","type":"bits","user":"weird\"name,","amount":100,"tier":null}"#
+                + "\n",
+        );
+
+        assert_eq!(ExportFormat::from_path("stats.JSONL"), ExportFormat::JsonLines);
+        assert_eq!(ExportFormat::from_path("stats.csv"), ExportFormat::Csv);
+    }
+}
@@ -2,7 +2,7 @@ use std::{
     collections::hash_map::{Entry, HashMap},
     fmt::{Display, Formatter},
 };
-use hexchat::{get_channel_name, get_current_channel, get_focused_channel};
+use crate::host::{get_channel_name, is_focused};
 
 
 /// The four possible colors for a HexChat tab, representing the types of events
@@ -44,7 +44,7 @@ impl Tabs {
     ///
     /// Input: `TabColor`
     pub fn color(&mut self, color_new: TabColor) {
-        if get_focused_channel() != Some(get_current_channel()) {
+        if !is_focused() {
             match self.inner.entry(get_channel_name()) {
                 Entry::Occupied(mut entry) => {
                     let color: &mut TabColor = entry.get_mut();
@@ -70,3 +70,27 @@ impl Tabs {
         cmd!("GUI COLOR {}", TabColor::RESET);
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::host::MockHost;
+    use super::*;
+
+    #[test]
+    fn test_tab_colors() {
+        let host = MockHost::new("#tabs").install();
+        let mut tabs = Tabs::default();
+
+        tabs.color(TabColor::Message);
+        tabs.color(TabColor::Event);
+        tabs.color(TabColor::Highlight);
+        assert_eq!(host.take_lines(), ["/GUI COLOR 2", "/GUI COLOR 3"]);
+
+        //  A focused tab is never colored.
+        host.focused.set(true);
+        tabs.reset();
+        tabs.color(TabColor::Highlight);
+        assert_eq!(host.take_lines(), ["/GUI COLOR 0"]);
+    }
+}
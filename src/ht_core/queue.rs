@@ -0,0 +1,253 @@
+//! Holding of chat messages sent by the user, in Channels with slow mode, or
+//!     when the rate limit of Twitch has been reached.
+//!
+//! Twitch drops a message sent before the slow mode cooldown has passed, or
+//!     one that would go over the rate limit. Rather than let that happen, a
+//!     message typed too early is put into a queue for its Channel, and a
+//!     HexChat `TIMER` is set to send it once the wait is over. Moderators and
+//!     broadcasters are not subject to slow mode, and have a higher rate limit.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+use parking_lot::Mutex;
+use super::{
+    outgoing,
+    output::{alert_basic, CHANNELS, USERSTATE},
+    ratelimit::{self, Traffic},
+};
+
+
+safe_static! {
+    static lazy QUEUES: Mutex<HashMap<String, Pending>> = Default::default();
+}
+
+
+/// A message waiting to be sent, and the ID of the message it replies to, if
+///     it is a reply.
+struct Queued {
+    text: String,
+    parent: Option<String>,
+}
+
+
+/// Messages waiting to be sent in one Channel.
+#[derive(Default)]
+struct Pending {
+    lines: VecDeque<Queued>,
+    /// Whether a `TIMER` has been set to send the next line.
+    scheduled: bool,
+}
+
+
+/// Time remaining before the user may speak again in a Channel, and the reason
+///     for it. Returns `None` if the Channel is not in slow mode, or if the
+///     user is exempt from it, and the rate limit has not been reached.
+///
+/// Input: `&str`, `Instant`
+/// Return: `Option<(Duration, &str)>`
+fn cooldown(channel: &str, now: Instant) -> Option<(Duration, &'static str)> {
+    let rate: Duration = ratelimit::wait(Traffic::Chat, Some(channel));
+
+    match slow_cooldown(channel, now) {
+        Some(slow) if slow >= rate => Some((slow, "Slow mode")),
+        _ if !rate.is_zero() => Some((rate, "Rate limit")),
+        _ => None,
+    }
+}
+
+
+/// Time remaining, as of a given moment, before slow mode allows the user to
+///     speak again.
+fn slow_cooldown(channel: &str, now: Instant) -> Option<Duration> {
+    if USERSTATE.is_op(channel) { return None; }
+
+    let data = CHANNELS.get(channel)?;
+    let delay: Duration = match data.roomstate.slow {
+        Some(sec) if sec > 0 => Duration::from_secs(sec as u64),
+        _ => return None,
+    };
+
+    Some(match data.last_sent {
+        Some(sent) => delay.saturating_sub(now.saturating_duration_since(sent)),
+        None => Duration::ZERO,
+    })
+}
+
+
+/// Set a `TIMER` to release the next message in a Channel. A second is added,
+///     to allow for the difference between local time and that of Twitch.
+fn schedule(channel: &str, wait: Duration) {
+    cmd!("TIMER {} HTQUEUE send {}", wait.as_secs() + 1, channel);
+}
+
+
+/// Decide whether a message typed by the user must wait. If it must, it is put
+///     into the queue, and `true` is returned. A message must also wait if
+///     there are others ahead of it, so that order is kept. If a parent
+///     message ID is given, the message is sent as a reply to it.
+///
+/// Input: `&str`, `&str`, `Option<&str>`
+/// Return: `bool`
+pub fn submit(channel: &str, text: &str, parent: Option<&str>) -> bool {
+    submit_at(channel, text, parent, Instant::now())
+}
+
+
+fn submit_at(channel: &str, text: &str, parent: Option<&str>, now: Instant) -> bool {
+    let (wait, reason) = match cooldown(channel, now) {
+        Some(cooldown) => cooldown,
+        None => return false,
+    };
+
+    let (count, schedule_now) = {
+        let mut queues = QUEUES.lock();
+        let pending: &mut Pending = queues.entry(channel.to_owned()).or_default();
+
+        if pending.lines.is_empty() && wait.is_zero() {
+            return false;
+        }
+
+        pending.lines.push_back(Queued {
+            text: text.to_owned(),
+            parent: parent.map(str::to_owned),
+        });
+        (pending.lines.len(), !std::mem::replace(&mut pending.scheduled, true))
+    };
+
+    if schedule_now {
+        schedule(channel, wait);
+    }
+
+    alert_basic(format!(
+        "{}: Message queued ({} waiting, next in {}s). Use /HTQUEUE to \
+        view or clear the queue.",
+        reason, count, wait.as_secs() + 1,
+    ));
+    true
+}
+
+
+/// Send the next message waiting in a Channel, if the cooldown has passed. If
+///     it has not, or if more messages are waiting, another `TIMER` is set.
+///
+/// Input: `&str`
+pub fn release(channel: &str) {
+    release_at(channel, Instant::now());
+}
+
+
+fn release_at(channel: &str, now: Instant) {
+    let wait: Duration = cooldown(channel, now).map_or(Duration::ZERO, |(wait, _)| wait);
+
+    let (line, more) = {
+        let mut queues = QUEUES.lock();
+        let pending: &mut Pending = match queues.get_mut(channel) {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        let line: Option<Queued> = match wait.is_zero() {
+            true => pending.lines.pop_front(),
+            false => None,
+        };
+
+        pending.scheduled = !pending.lines.is_empty();
+        (line, pending.scheduled)
+    };
+
+    if let Some(Queued { text, parent }) = line {
+        outgoing::say(channel, &text, parent.as_deref());
+    }
+
+    if more {
+        let next: Option<(Duration, &str)> = cooldown(channel, now);
+        schedule(channel, next.map_or(Duration::ZERO, |(wait, _)| wait));
+    }
+}
+
+
+/// Remove every message waiting in a Channel.
+///
+/// Input: `&str`
+/// Return: `usize`
+pub fn clear(channel: &str) -> usize {
+    QUEUES.lock().remove(channel).map_or(0, |pending| pending.lines.len())
+}
+
+
+/// Messages waiting in a Channel, in the order they will be sent.
+///
+/// Input: `&str`
+/// Return: `Vec<String>`
+pub fn waiting(channel: &str) -> Vec<String> {
+    match QUEUES.lock().get(channel) {
+        Some(pending) => pending.lines.iter().map(|q| q.text.clone()).collect(),
+        None => Vec::new(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use crate::host::MockHost;
+    use super::*;
+
+    #[test]
+    fn test_queue() {
+        const CHANNEL: &str = "#queued";
+        let host = MockHost::new(CHANNEL).install();
+        let now: Instant = Instant::now();
+        let ago = |secs: f64| now.checked_sub(Duration::from_secs_f64(secs));
+
+        //  Without slow mode, nothing is held.
+        assert!(!submit_at(CHANNEL, "hello", None, now));
+
+        {
+            let mut data = CHANNELS.ensure(CHANNEL.into());
+            data.roomstate.slow = Some(30);
+            data.last_sent = ago(10.5);
+        }
+
+        assert!(submit_at(CHANNEL, "first", None, now));
+        assert!(submit_at(CHANNEL, "second", Some("abc-123"), now));
+        assert_eq!(waiting(CHANNEL), ["first", "second"]);
+
+        let timers = |lines: &[String]| lines.iter()
+            .filter(|l| l.starts_with("/TIMER ") && l.ends_with(" HTQUEUE send #queued"))
+            .count();
+
+        let lines: Vec<String> = host.take_lines();
+        assert_eq!(timers(&lines), 1);
+        assert!(lines.contains(&String::from("/TIMER 20 HTQUEUE send #queued")));
+
+        //  Nothing is sent before the cooldown is over.
+        release_at(CHANNEL, now);
+        assert_eq!(waiting(CHANNEL).len(), 2);
+        host.take();
+
+        CHANNELS.ensure(CHANNEL.into()).last_sent = ago(60.0);
+        release_at(CHANNEL, now);
+        assert_eq!(waiting(CHANNEL), ["second"]);
+
+        let lines: Vec<String> = host.take_lines();
+        assert!(lines[0].starts_with("/QUOTE @client-nonce="));
+        assert!(lines[0].ends_with(" PRIVMSG #queued :first"));
+        assert_eq!(timers(&lines), 1);
+
+        //  A reply keeps its parent while it waits.
+        CHANNELS.ensure(CHANNEL.into()).last_sent = ago(60.0);
+        release_at(CHANNEL, now);
+        assert!(waiting(CHANNEL).is_empty());
+
+        let lines: Vec<String> = host.take_lines();
+        assert!(lines[0].starts_with("/QUOTE @client-nonce="));
+        assert!(lines[0].contains(";reply-parent-msg-id=abc-123 "));
+        assert!(lines[0].ends_with(" PRIVMSG #queued :second"));
+
+        assert!(submit_at(CHANNEL, "third", None, now));
+        assert_eq!(clear(CHANNEL), 1);
+        assert!(waiting(CHANNEL).is_empty());
+    }
+}
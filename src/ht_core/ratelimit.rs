@@ -0,0 +1,149 @@
+//! Accounting of messages sent to Twitch, to stay within its rate limits.
+//!
+//! Twitch allows an account to send 20 chat messages in 30 seconds, or 100 in
+//!     a Channel where it is a moderator, and silently drops anything beyond
+//!     that. Whispers have limits of their own. Every message sent is recorded
+//!     in a sliding window for the account, so that one which would go over a
+//!     limit can be delayed or refused instead.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+use parking_lot::Mutex;
+use crate::host::get_nickname;
+use super::output::USERSTATE;
+
+
+safe_static! {
+    static lazy WINDOWS: Mutex<HashMap<(String, Traffic), Window>> = Default::default();
+}
+
+
+/// A kind of message, with its own rate limits.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Traffic {
+    Chat,
+    Whisper,
+}
+
+
+/// The most messages that may be sent within a period of time.
+struct Limit {
+    count: usize,
+    window: Duration,
+}
+
+const LIMITS_CHAT: &[Limit] = &[
+    Limit { count: 20, window: Duration::from_secs(30) },
+];
+const LIMITS_CHAT_OP: &[Limit] = &[
+    Limit { count: 100, window: Duration::from_secs(30) },
+];
+const LIMITS_WHISPER: &[Limit] = &[
+    Limit { count: 3, window: Duration::from_secs(1) },
+    Limit { count: 100, window: Duration::from_secs(60) },
+];
+
+
+/// The limits that apply to a message. For chat, these depend on whether the
+///     user is a moderator in the Channel it is sent to.
+fn limits(traffic: Traffic, channel: Option<&str>) -> &'static [Limit] {
+    match traffic {
+        Traffic::Chat if channel.is_some_and(|c| USERSTATE.is_op(c)) => LIMITS_CHAT_OP,
+        Traffic::Chat => LIMITS_CHAT,
+        Traffic::Whisper => LIMITS_WHISPER,
+    }
+}
+
+
+/// Times at which recent messages were sent, oldest first.
+#[derive(Default)]
+struct Window { sent: VecDeque<Instant> }
+
+impl Window {
+    /// Time to wait before another message may be sent within all of the given
+    ///     limits. Zero if one may be sent now.
+    fn wait(&mut self, limits: &[Limit], now: Instant) -> Duration {
+        //  Nothing older than the longest window can matter any more.
+        if let Some(longest) = limits.iter().map(|limit| limit.window).max() {
+            while self.sent.front().is_some_and(|&t| now.duration_since(t) >= longest) {
+                self.sent.pop_front();
+            }
+        }
+
+        limits.iter().filter_map(|limit| {
+            //  If the limit has been reached, the next message may be sent once
+            //      the oldest one counting against it leaves the window.
+            let idx: usize = self.sent.len().checked_sub(limit.count)?;
+            let oldest: Instant = self.sent[idx];
+
+            Some((oldest + limit.window).saturating_duration_since(now))
+        }).max().unwrap_or_default()
+    }
+
+    fn record(&mut self, now: Instant) {
+        self.sent.push_back(now);
+    }
+}
+
+
+/// Time to wait before a message may be sent without exceeding a rate limit.
+///     Zero if one may be sent now.
+///
+/// Input: [`Traffic`], `Option<&str>`
+/// Return: `Duration`
+pub fn wait(traffic: Traffic, channel: Option<&str>) -> Duration {
+    let limits: &[Limit] = limits(traffic, channel);
+
+    WINDOWS.lock()
+        .entry((get_nickname(), traffic))
+        .or_default()
+        .wait(limits, Instant::now())
+}
+
+
+/// Record that a message has been sent.
+///
+/// Input: [`Traffic`]
+pub fn record(traffic: Traffic) {
+    WINDOWS.lock()
+        .entry((get_nickname(), traffic))
+        .or_default()
+        .record(Instant::now());
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window() {
+        let mut window = Window::default();
+        let t0 = Instant::now();
+        let ms = |n: u64| t0 + Duration::from_millis(n);
+
+        for i in 0..3 {
+            assert_eq!(window.wait(LIMITS_WHISPER, ms(i * 100)), Duration::ZERO);
+            window.record(ms(i * 100));
+        }
+
+        //  A fourth Whisper within a second must wait for the first to expire.
+        assert_eq!(window.wait(LIMITS_WHISPER, ms(500)), Duration::from_millis(500));
+        assert_eq!(window.wait(LIMITS_WHISPER, ms(1000)), Duration::ZERO);
+
+        //  A moderator may send more chat messages than anyone else.
+        let mut window = Window::default();
+
+        for i in 0..20 {
+            window.record(ms(i * 10));
+        }
+
+        assert_eq!(window.wait(LIMITS_CHAT_OP, ms(200)), Duration::ZERO);
+        assert_eq!(window.wait(LIMITS_CHAT, ms(200)), Duration::from_millis(29_800));
+        assert_eq!(window.wait(LIMITS_CHAT, ms(29_995)), Duration::from_millis(5));
+        assert_eq!(window.wait(LIMITS_CHAT, ms(30_000)), Duration::ZERO);
+        assert_eq!(window.sent.len(), 19);
+    }
+}
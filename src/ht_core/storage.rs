@@ -1,8 +1,25 @@
 //! Storage of IRC [`Message`]s across the period between reception and output.
-
+//!
+//! Messages are kept in a short queue, and each print event takes the oldest
+//!     one with the same [`Signature`]. This way, a print event that never
+//!     arrives, or one that arrives late, cannot cause the context of one
+//!     Message to be attached to another.
+//!
+//! Lines are kept as they were received, and only parsed into an owned Message
+//!     when a print event takes one. Most of them are never taken.
+
+use std::{collections::VecDeque, time::{Duration, Instant}};
 use hexchat::EatMode;
 use parking_lot::Mutex;
-use crate::irc::Message;
+use crate::{host::print_plain, irc::{Message, MessageRef, Signature}, prefs::*};
+
+
+/// Most Messages that may be waiting at once. When another arrives, the oldest
+///     is dropped.
+const QUEUE_MAX: usize = 16;
+
+/// Time after which a Message is assumed to have no print event coming.
+const EXPIRY: Duration = Duration::from_secs(5);
 
 
 safe_static! {
@@ -28,15 +45,51 @@ pub fn ignore_next_print_event() {
 // }
 
 
-/// Get the IRC [`Message`], if it is set.
-pub fn recover_message() -> Action {
-    CURRENT.lock().get()
+/// Get the IRC [`Message`] matching a print event, if there is one. In debug
+///     mode, Messages passed over to find it are reported.
+///
+/// Input: [`Signature`]
+/// Return: [`Action`]
+pub fn recover_message(sig: &Signature) -> Action {
+    let (action, skipped) = CURRENT.lock().get(sig, Instant::now());
+
+    if PREF_DEBUG.is(&true) {
+        match &action {
+            Action::ProcPrint if !skipped.is_empty() => print_plain(&format!(
+                "No stored Message matches {}; waiting: {}",
+                sig,
+                skipped.join(", "),
+            )),
+            Action::ProcIrc(_) if !skipped.is_empty() => print_plain(&format!(
+                "Stored Message for {} was not first in line, after: {}",
+                sig,
+                skipped.join(", "),
+            )),
+            _ => {}
+        }
+    }
+
+    action
 }
 
 
-/// Store a new IRC [`Message`], to be retrieved during a later print event.
-pub fn store_message(msg: Message) {
-    CURRENT.lock().put(msg);
+/// Get the IRC [`Message`] matching a print event, if there is one, without
+///     regard for whether the event should be eaten or ignored. Used for
+///     events which are not chat messages, such as Server Notices.
+///
+/// Input: [`Signature`]
+/// Return: `Option<Message>`
+pub fn recover_context(sig: &Signature) -> Option<Message> {
+    CURRENT.lock().take(sig, Instant::now())
+}
+
+
+/// Store a new IRC Message, to be retrieved during a later print event. The
+///     raw line must be the one that the [`MessageRef`] was parsed from.
+///
+/// Input: [`MessageRef`], `&str`
+pub fn store_message(msg: &MessageRef, raw: &str) {
+    CURRENT.lock().put(msg, raw, Instant::now());
 }
 
 
@@ -51,28 +104,133 @@ pub enum Action {
 }
 
 
+struct Pending {
+    sig: Signature,
+    time: Instant,
+    /// The raw line, to be parsed again if it is taken.
+    line: String,
+}
+
+
 #[derive(Default)]
 struct Storage {
-    irc: Option<Message>,
+    queue: VecDeque<Pending>,
     next: Option<EatMode>,
 }
 
 impl Storage {
-    fn get(&mut self) -> Action {
-        match self.next.take() {
-            Some(eat) => Action::Eat(eat),
-            None => match self.irc.take() {
-                Some(msg) => Action::ProcIrc(msg),
-                None => Action::ProcPrint,
-            }
+    /// Determine the Action for a print event. Also returns the Signatures of
+    ///     any unexpired Messages ahead of the one taken, or of all of them if
+    ///     none matched.
+    fn get(&mut self, sig: &Signature, now: Instant) -> (Action, Vec<String>) {
+        if let Some(eat) = self.next.take() {
+            return (Action::Eat(eat), Vec::new());
+        }
+
+        self.expire(now);
+
+        let skipped: Vec<String> = self.queue.iter()
+            .take_while(|p| &p.sig != sig)
+            .map(|p| p.sig.to_string())
+            .collect();
+
+        match self.take(sig, now) {
+            Some(msg) => (Action::ProcIrc(msg), skipped),
+            None => (Action::ProcPrint, skipped),
         }
     }
 
-    fn put(&mut self, msg: Message) {
-        self.irc = Some(msg);
+    /// Remove the oldest unexpired Message with a given Signature.
+    fn take(&mut self, sig: &Signature, now: Instant) -> Option<Message> {
+        self.expire(now);
+
+        let found: usize = self.queue.iter().position(|p| &p.sig == sig)?;
+        let pending: Pending = self.queue.remove(found)?;
+
+        MessageRef::parse(&pending.line).ok().map(|msg| msg.to_message())
+    }
+
+    fn put(&mut self, msg: &MessageRef, raw: &str, now: Instant) {
+        self.expire(now);
+
+        if self.queue.len() >= QUEUE_MAX {
+            self.queue.pop_front();
+        }
+
+        self.queue.push_back(Pending {
+            sig: msg.get_signature(),
+            time: now,
+            line: raw.to_owned(),
+        });
     }
 
     fn set_next(&mut self, eat: EatMode) {
         self.next = Some(eat);
     }
+
+    /// Drop every Message which has been waiting too long.
+    fn expire(&mut self, now: Instant) {
+        while self.queue.front().is_some_and(|p| now.duration_since(p.time) > EXPIRY) {
+            self.queue.pop_front();
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn privmsg(channel: &str, author: &str, text: &str) -> String {
+        format!(":{author}!{author}@{author}.tmi.twitch.tv PRIVMSG {channel} :{text}")
+    }
+
+    fn put(storage: &mut Storage, line: String, now: Instant) {
+        storage.put(&MessageRef::parse(&line).unwrap(), &line, now);
+    }
+
+    fn sig(channel: &str, author: &str) -> Signature {
+        Signature::new(Some(channel), Ok(author))
+    }
+
+    fn text(action: (Action, Vec<String>)) -> Option<String> {
+        match action.0 {
+            Action::ProcIrc(msg) => Some(msg.text().to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_storage() {
+        let mut storage = Storage::default();
+        let t0 = Instant::now();
+
+        put(&mut storage, privmsg("#asdf", "alice", "first"), t0);
+        put(&mut storage, privmsg("#asdf", "bob", "second"), t0);
+        put(&mut storage, privmsg("#qwert", "alice", "third"), t0);
+
+        //  Messages are taken by Signature, not by order of arrival.
+        let (action, skipped) = storage.get(&sig("#asdf", "bob"), t0);
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(text((action, skipped)).as_deref(), Some("second"));
+        assert_eq!(text(storage.get(&sig("#qwert", "alice"), t0)).as_deref(), Some("third"));
+        assert_eq!(text(storage.get(&sig("#qwert", "alice"), t0)), None);
+
+        //  An explicit instruction takes precedence over any stored Message.
+        storage.set_next(EatMode::All);
+        assert!(matches!(storage.get(&sig("#asdf", "alice"), t0).0, Action::Eat(EatMode::All)));
+        assert_eq!(text(storage.get(&sig("#asdf", "alice"), t0)).as_deref(), Some("first"));
+
+        //  Old Messages are not kept forever.
+        put(&mut storage, privmsg("#asdf", "alice", "stale"), t0);
+        let later = t0 + EXPIRY + Duration::from_secs(1);
+        assert_eq!(text(storage.get(&sig("#asdf", "alice"), later)), None);
+
+        //  Nor are too many of them.
+        for i in 0..=QUEUE_MAX {
+            put(&mut storage, privmsg("#asdf", &format!("user{i}"), "spam"), later);
+        }
+        assert_eq!(storage.queue.len(), QUEUE_MAX);
+        assert_eq!(text(storage.get(&sig("#asdf", "user0"), later)), None);
+    }
 }
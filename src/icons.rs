@@ -1,5 +1,5 @@
 use std::{fs::{create_dir_all, File}, io::Write, path::{Path, PathBuf}};
-use hexchat::get_config_dir;
+use crate::host::get_config_dir;
 
 
 const DIR_ICONS: &str = "icons_hextwitch";
@@ -53,7 +53,7 @@ impl Icon {
         path.push(self.path);
 
         #[cfg(feature = "full-debug")]
-        hexchat::print_plain(&path.display().to_string());
+        crate::host::print_plain(&path.display().to_string());
 
         if !path.exists() {
             let parent: &Path = path.parent()?;
@@ -1,12 +1,20 @@
 //! Module for the splitting of IRCv3 strings into a more usable format.
 
 use std::{
-    collections::HashMap,
-    convert::Infallible,
+    error::Error,
     fmt::{Display, Formatter},
 };
 
 
+/// The longest line that will be accepted by the parser, in bytes.
+///
+/// IRCv3 allows up to 8191 bytes for the Tags segment. The remainder of a line
+///     is limited to 512 bytes by RFC 1459, but Twitch does not hold to that;
+///     A chat message of 500 characters can take up to 2000 bytes of UTF-8.
+///     This leaves a generous margin above both.
+pub const MAX_LINE_LEN: usize = 8191 + 4096;
+
+
 /// Given a string which may contain characters which are not allowed in an IRC
 ///     Tag String, replace all such characters with escaped substitutions.
 ///
@@ -52,11 +60,12 @@ pub fn unescape(line: &str) -> String {
                 Some('r') => out.push('\r'),
                 Some('s') => out.push(' '),
                 Some(':') => out.push(';'),
-                None => out.push(first),
-                Some(other) => {
-                    out.push(first);
-                    out.push(other);
-                }
+                //  Per the IRCv3 specification, a backslash which does not
+                //      begin a valid escape is dropped, and the character after
+                //      it is kept as-is. A lone backslash at the end of the
+                //      value is dropped entirely.
+                Some(other) => out.push(other),
+                None => {}
             }
         } else { out.push(first) }
     }
@@ -119,15 +128,186 @@ pub fn split_at_char(line: &str, delim: char) -> (&str, &str) {
 }
 
 
-/// Split a `&str` at the first occurrence of another delimiting `&str`.
-fn split_at_str<'a>(line: &'a str, delim: &str) -> (&'a str, &'a str) {
-    match line.find(delim) {
-        Some(idx) => (&line[..idx], &line[idx + delim.len()..]),
-        None => (line, ""),
+/// The parts of an IRCv3 Tag key: `[+][<vendor>/]<name>`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TagKey<'a> {
+    /// Whether the key begins with `+`, marking a Tag which is sent by clients
+    ///     and relayed by the server without being interpreted.
+    pub client_only: bool,
+    /// The vendor namespace of the key, if it has one, such as `draft`.
+    pub vendor: Option<&'a str>,
+    /// The name of the key, without any prefix.
+    pub name: &'a str,
+}
+
+impl<'a> TagKey<'a> {
+    /// Split a Tag key into its parts. Returns `None` if the key is malformed.
+    ///
+    /// Only the structure of the key is checked, rather than every character,
+    ///     because Twitch is not known to hold strictly to the specification.
+    ///
+    /// Input: `&str`
+    /// Return: `Option<TagKey>`
+    pub fn parse(key: &'a str) -> Option<Self> {
+        let (client_only, rest) = match key.strip_prefix('+') {
+            Some(rest) => (true, rest),
+            None => (false, key),
+        };
+        let (vendor, name) = match rest.split_once('/') {
+            Some((vendor, name)) => (Some(vendor), name),
+            None => (None, rest),
+        };
+
+        if name.is_empty()
+            || name.contains(&['/', '+', '='][..])
+            || vendor == Some("")
+        {
+            None
+        } else {
+            Some(Self { client_only, vendor, name })
+        }
+    }
+}
+
+
+/// A single IRCv3 Tag. The value is kept escaped, exactly as it was received,
+///     so that a Message can be reproduced without any change.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Tag {
+    pub key: String,
+    /// The escaped value of the Tag. This will be `None` if the key was not
+    ///     followed by `=` at all, which is distinct from an empty value.
+    pub value: Option<String>,
+}
+
+impl Tag {
+    /// Return the escaped value of this Tag. A missing value is equivalent to
+    ///     an empty one.
+    pub fn value_raw(&self) -> &str {
+        self.value.as_deref().unwrap_or_default()
+    }
+
+    /// Split the key of this Tag into its parts.
+    pub fn key_parts(&self) -> Option<TagKey<'_>> {
+        TagKey::parse(&self.key)
+    }
+}
+
+impl Display for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{}={}", self.key, value),
+            None => f.write_str(&self.key),
+        }
+    }
+}
+
+
+/// The IRCv3 Tags of a Message, in the order they were received.
+///
+/// If a key is repeated, the last value is the one that applies, but all of
+///     them are kept so that the Tags are reproduced exactly.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Tags(Vec<Tag>);
+
+impl Tags {
+    /// Retrieve the escaped value of a Tag.
+    ///
+    /// Input: `&str`
+    /// Return: `Option<&str>`
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.0.iter().rev().find(|tag| tag.key == key).map(Tag::value_raw)
+    }
+
+    /// Set the escaped value of a Tag. If the Tag was already present, its old
+    ///     escaped value is returned.
+    ///
+    /// Input: `&str`, `String`
+    /// Return: `Option<String>`
+    pub fn insert(&mut self, key: &str, value: String) -> Option<String> {
+        match self.0.iter_mut().rev().find(|tag| tag.key == key) {
+            Some(tag) => Some(tag.value.replace(value).unwrap_or_default()),
+            None => {
+                self.0.push(Tag { key: key.to_owned(), value: Some(value) });
+                None
+            }
+        }
+    }
+
+    /// Iterate over the Tags, in their original order.
+    pub fn iter(&self) -> impl Iterator<Item=&Tag> { self.0.iter() }
+
+    /// Iterate over the Tags whose keys begin with `+`. These are sent by other
+    ///     clients, rather than by the server.
+    pub fn client_only(&self) -> impl Iterator<Item=&Tag> {
+        self.0.iter().filter(|tag| tag.key.starts_with('+'))
+    }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+    pub fn len(&self) -> usize { self.0.len() }
+}
+
+impl Display for Tags {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut iter = self.0.iter();
+
+        if let Some(first) = iter.next() {
+            first.fmt(f)?;
+
+            for tag in iter {
+                write!(f, ";{}", tag)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'t> FromIterator<(&'t str, Option<&'t str>)> for Tags {
+    fn from_iter<I>(iter: I) -> Self where
+        I: IntoIterator<Item=(&'t str, Option<&'t str>)>,
+    {
+        Self(iter.into_iter().map(|(key, value)| Tag {
+            key: key.to_owned(),
+            value: value.map(str::to_owned),
+        }).collect())
     }
 }
 
 
+/// A reason that a raw line could not be parsed as an IRC Message.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IrcParseError {
+    /// The line is empty, or contains only whitespace.
+    Empty,
+    /// The line is longer than [`MAX_LINE_LEN`]. Contains the actual length.
+    TooLong(usize),
+    /// The line has a Prefix marker, but the Prefix is empty or malformed.
+    BadPrefix,
+    /// The line has a Tags marker, but the Tags segment is malformed.
+    BadTags,
+    /// The line does not contain a Command.
+    NoCommand,
+}
+
+impl Display for IrcParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => f.write_str("line is empty"),
+            Self::TooLong(len) => write!(
+                f, "line is {} bytes long, exceeding the limit of {}",
+                len, MAX_LINE_LEN,
+            ),
+            Self::BadPrefix => f.write_str("prefix is malformed"),
+            Self::BadTags => f.write_str("tags are malformed"),
+            Self::NoCommand => f.write_str("line has no command"),
+        }
+    }
+}
+
+impl Error for IrcParseError {}
+
+
 #[derive(Clone, Debug, Hash, Eq, PartialEq)]
 pub struct Signature(String);
 
@@ -165,6 +345,21 @@ pub enum Prefix {
 }
 
 impl Prefix {
+    fn new(nick_user_host: &str) -> Self {
+        if nick_user_host.contains('.') && !nick_user_host.contains('@') {
+            Self::ServerName(String::from(nick_user_host))
+        } else {
+            let (nick_user, hostname) = split_at_byte(nick_user_host, b'@');
+            let (nickname, username) = split_at_byte(nick_user, b'!');
+
+            Self::User {
+                nick: String::from(nickname),
+                user: owned_not_empty(username),
+                host: owned_not_empty(hostname),
+            }
+        }
+    }
+
     /// Name: Return a representation of the author of a Message, intended for a
     ///     human to read. If the Prefix is a Server Name, it will be that; If
     ///     it is instead a User String, it will be the Nick.
@@ -205,24 +400,17 @@ impl Display for Prefix {
 }
 
 impl std::str::FromStr for Prefix {
-    type Err = Infallible;
+    type Err = IrcParseError;
 
     /// Split an IRC Prefix string into an Author.
     ///
     /// Input: `&str`
-    /// Return: `Result<Prefix, !>`
+    /// Return: `Result<Prefix, IrcParseError>`
     fn from_str(nick_user_host: &str) -> Result<Self, Self::Err> {
-        if nick_user_host.contains('.') && !nick_user_host.contains('@') {
-            Ok(Self::ServerName(String::from(nick_user_host)))
+        if is_bad_prefix(nick_user_host) {
+            Err(IrcParseError::BadPrefix)
         } else {
-            let (nick_user, hostname) = split_at_byte(nick_user_host, b'@');
-            let (nickname, username) = split_at_byte(nick_user, b'!');
-
-            Ok(Self::User {
-                nick: String::from(nickname),
-                user: owned_not_empty(username),
-                host: owned_not_empty(hostname),
-            })
+            Ok(Self::new(nick_user_host))
         }
     }
 }
@@ -237,11 +425,17 @@ pub struct Message {
     pub command: String,
     /// Arguments passed to the Command.
     pub args: Vec<String>,
-    /// Remainder of the Message.
-    pub trail: String,
-    /// IRCv3 Tags. Strings mapped to Strings. This will be `None` if the
+    /// Remainder of the Message. This will be `None` if the original message
+    ///     did not include a trailing parameter, which is distinct from an
+    ///     empty one.
+    pub trail: Option<String>,
+    /// IRCv3 Tags, in their original order. This will be `None` if the
     ///     original message did not include a Tags segment.
-    pub tags: Option<HashMap<String, String>>,
+    pub tags: Option<Tags>,
+    /// The Arguments exactly as they were received, if they were spaced in
+    ///     some irregular way. This is only used to reproduce the original
+    ///     line, and only while it still agrees with `args`.
+    args_raw: Option<String>,
 }
 
 impl Message {
@@ -263,6 +457,12 @@ impl Message {
     #[inline]
     pub fn has_tags(&self) -> bool { self.tags.is_some() }
 
+    /// Text: Return the trailing parameter of this Message, or an empty string
+    ///     if it has none.
+    ///
+    /// Return: `&str`
+    pub fn text(&self) -> &str { self.trail.as_deref().unwrap_or_default() }
+
     /// Retrieve a Tag from the `Message`.
     ///
     /// Input: `&str`
@@ -283,7 +483,8 @@ impl Message {
                    -> Result<Option<String>, ()>
     {
         self.tags.as_mut().ok_or(())
-            .map(|tags| tags.insert(String::from(key), escape(value)))
+            .map(|tags| tags.insert(key, escape(value)))
+            .map(|old| old.as_deref().map(unescape))
     }
 }
 
@@ -294,57 +495,207 @@ impl Display for Message {
     /// Return: `fmt::Result`
     fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
         if let Some(tags) = &self.tags {
-            let mut tagseq: Vec<String> = tags.iter()
-                .map(|(key, val)|
-                    if val.is_empty() {
-                        key.to_owned()
-                    } else {
-                        format!("{}={}", key, val)
-                    }
-                )
-                .collect();
-
-            tagseq.sort_unstable();
-            write!(f, "@{} ", tagseq.join(";"))?;
+            if !tags.is_empty() {
+                write!(f, "@{} ", tags)?;
+            }
         }
 
-        write!(f, ":{} {}", self.prefix, self.command)?;
-        for arg in &self.args { write!(f, " {}", arg)?; }
-        if !self.trail.is_empty() { write!(f, " :{}", self.trail)?; }
+        if !self.prefix.name().is_empty() {
+            write!(f, ":{} ", self.prefix)?;
+        }
+
+        f.write_str(&self.command)?;
+
+        match &self.args_raw {
+            Some(raw) if raw.split_ascii_whitespace().eq(self.args.iter()) => {
+                f.write_str(raw)?;
+            }
+            _ => for arg in &self.args { write!(f, " {}", arg)?; }
+        }
+
+        if let Some(trail) = &self.trail { write!(f, " :{}", trail)?; }
 
         Ok(())
     }
 }
 
+
 impl std::str::FromStr for Message {
-    type Err = Infallible;
+    type Err = IrcParseError;
 
     /// Split a raw IRC string into a usable `Message`.
     ///
     /// Input: `&str`
-    /// Return: `Result<Message, !>`
+    /// Return: `Result<Message, IrcParseError>`
     fn from_str(full_str: &str) -> Result<Self, Self::Err> {
+        Ok(MessageRef::parse(full_str)?.to_message())
+    }
+}
+
+impl<'a> From<MessageRef<'a>> for Message {
+    fn from(msg: MessageRef<'a>) -> Self { msg.to_message() }
+}
+
+
+/// A builder for outgoing IRC Messages, which may carry IRCv3 Tags.
+///
+/// Outgoing lines do not have a Prefix; The server fills in the source itself.
+#[derive(Debug)]
+pub struct MessageBuilder {
+    tags: Tags,
+    command: String,
+    args: Vec<String>,
+    trail: Option<String>,
+}
+
+impl MessageBuilder {
+    pub fn new(command: &str) -> Self {
+        Self {
+            tags: Tags::default(),
+            command: String::from(command),
+            args: Vec::new(),
+            trail: None,
+        }
+    }
+
+    /// Add an Argument. Arguments are written in the order they are added.
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.args.push(String::from(arg));
+        self
+    }
+
+    /// Set a Tag. The value is given unescaped, and will be escaped here.
+    pub fn tag(mut self, key: &str, value: &str) -> Self {
+        self.tags.insert(key, escape(value));
+        self
+    }
+
+    /// Set the trailing parameter.
+    pub fn trail(mut self, text: &str) -> Self {
+        self.trail = Some(String::from(text));
+        self
+    }
+
+    /// Finish the [`Message`]. It will only have a Tags segment if at least
+    ///     one Tag was set.
+    pub fn build(self) -> Message {
+        Message {
+            prefix: Prefix::new(""),
+            command: self.command,
+            args: self.args,
+            trail: self.trail,
+            tags: Some(self.tags).filter(|tags| !tags.is_empty()),
+            args_raw: None,
+        }
+    }
+}
+
+
+/// Check whether a raw Prefix string cannot possibly be valid.
+fn is_bad_prefix(nick_user_host: &str) -> bool {
+    nick_user_host.is_empty()
+        || nick_user_host.starts_with::<&[char]>(&['!', '@'])
+        || nick_user_host.contains(' ')
+}
+
+
+/// Split a single raw Tag into its key and its escaped value. The value is
+///     `None` if there is no `=`, and `Some("")` if there is nothing after it.
+fn split_tag(kvp: &str) -> (&str, Option<&str>) {
+    match kvp.split_once('=') {
+        Some((key, value)) => (key, Some(value)),
+        None => (kvp, None),
+    }
+}
+
+
+/// Check whether a raw Tags string, without its leading `@`, is malformed.
+fn is_bad_tags(tag_str: &str) -> bool {
+    tag_str.is_empty()
+        || tag_str.split(';').any(|kvp| TagKey::parse(split_tag(kvp).0).is_none())
+}
+
+
+/// Check whether a raw Arguments string is written in the normal form, with a
+///     single space before each Argument and nothing after the last one.
+fn is_regular_args(args_str: &str) -> bool {
+    args_str.is_empty() || args_str.strip_prefix(' ').is_some_and(|rest| {
+        rest.split(' ').all(|arg| {
+            !arg.is_empty() && !arg.contains(|c: char| c.is_ascii_whitespace())
+        })
+    })
+}
+
+
+/// Find the human-readable name within a raw Prefix string, without splitting
+///     it into a [`Prefix`]. This follows the same rules as [`Prefix::name`].
+fn prefix_name(nick_user_host: &str) -> &str {
+    if nick_user_host.contains('.') && !nick_user_host.contains('@') {
+        nick_user_host
+    } else {
+        let (nick_user, _) = split_at_byte(nick_user_host, b'@');
+        let (nickname, _) = split_at_byte(nick_user, b'!');
+        nickname
+    }
+}
+
+
+/// A borrowed view of an IRC Message. Every field is a slice of the original
+///     line, so parsing one does not allocate. IRC Tags are only searched, and
+///     unescaped, when they are requested.
+///
+/// When a Message needs to be kept beyond the life of the line, it can be
+///     converted into an owned [`Message`] with [`MessageRef::to_message`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MessageRef<'a> {
+    /// UserString: `nick[!user][@host]`
+    pub prefix: &'a str,
+    /// IRC Command.
+    pub command: &'a str,
+    /// Arguments passed to the Command, not yet split apart. This includes the
+    ///     whitespace before the first and after the last.
+    args: &'a str,
+    /// Remainder of the Message. This will be `None` if the original message
+    ///     did not include a trailing parameter.
+    pub trail: Option<&'a str>,
+    /// IRCv3 Tags, still in their raw form, without the leading `@`. This will
+    ///     be `None` if the original message did not include a Tags segment.
+    tags: Option<&'a str>,
+}
+
+impl<'a> MessageRef<'a> {
+    /// Split a raw IRC string into a borrowed `MessageRef`.
+    ///
+    /// Input: `&str`
+    /// Return: `Result<MessageRef, IrcParseError>`
+    pub fn parse(full_str: &'a str) -> Result<Self, IrcParseError> {
+        if full_str.len() > MAX_LINE_LEN {
+            return Err(IrcParseError::TooLong(full_str.len()));
+        } else if full_str.trim().is_empty() {
+            return Err(IrcParseError::Empty);
+        }
+
         //  "@badges=bits/100;display-name=AsdfQwert;emotes= :asdfqwert!asdfqwert@twitch.tv PRIVMSG #zxcv arg2 :this is a message"
 
         //  Break the line down.
-        let (full_message, tags): (&str, Option<HashMap<String, String>>) = {
+        let (full_message, tags): (&str, Option<&str>) = {
             if first_byte_eq(full_str, b'@') {
                 //  The Tags String is the first half of the original message
                 //      received by IRC. The "regular" message begins after the
                 //      first space.
-                let (tag_str, main_str) = split_at_byte(&full_str, b' ');
+                let (tag_str, main_str) = split_at_byte(full_str, b' ');
+                let tag_str: &str = &tag_str[1..];
 
-                let tag_map = tag_str[1..].split(';').map(|kvp| {
-                    let (key, val) = split_at_byte(kvp, b'=');
-                    (key.to_owned(), val.to_owned())
-                }).collect();
+                if is_bad_tags(tag_str) {
+                    return Err(IrcParseError::BadTags);
+                }
 
-                (main_str, Some(tag_map))
+                (main_str, Some(tag_str))
             } else {
                 (full_str, None)
             }
         };
-        //  { badges: "bits/100", display-name: "AsdfQwert", emotes: "" }
+        //  "badges=bits/100;display-name=AsdfQwert;emotes="
         //  ":asdfqwert!asdfqwert@twitch.tv PRIVMSG #zxcv arg2 :this is a message"
 
         //  Now, parse the message itself.
@@ -352,7 +703,13 @@ impl std::str::FromStr for Message {
         let (prefix, message) = if first_byte_eq(full_message, b':') {
             //  This Message has a Prefix. The Prefix is most likely hostname
             //      and/or server info. It ends at the first space.
-            split_at_byte(&full_message[1..], b' ')
+            let (prefix, message) = split_at_byte(&full_message[1..], b' ');
+
+            if is_bad_prefix(prefix) {
+                return Err(IrcParseError::BadPrefix);
+            }
+
+            (prefix, message)
         } else {
             ("", full_message)
         };
@@ -361,29 +718,118 @@ impl std::str::FromStr for Message {
 
         //  The trailing data is found after a space and a colon. Everything up
         //      to that point is the IRC Command and any Arguments passed to it.
-        let (cmd_and_args, trail) = split_at_str(message, " :");
+        let (cmd_and_args, trail) = match message.split_once(" :") {
+            Some((cmd_and_args, trail)) => (cmd_and_args, Some(trail)),
+            None => (message, None),
+        };
         //  "PRIVMSG #zxcv arg2"
         //  "this is a message"
 
         //  The Command is the first word before any Arguments.
-        let (command, args_str) = split_at_byte(cmd_and_args, b' ');
+        let (command, _) = split_at_byte(cmd_and_args, b' ');
+        let args: &str = &cmd_and_args[command.len()..];
         //  "PRIVMSG"
-        //  "#zxcv arg2"
-
-        //  The Arguments should be split apart into a Vector of `String`s.
-        let args: Vec<String> = args_str.split_ascii_whitespace()
-            .map(String::from)
-            .collect();
-        //  ["#zxcv", "arg2"]
-
-        //  Compile everything into a Message Struct, and send it back up.
-        Ok(Self {
-            prefix: prefix.parse()?,  // "asdfqwert!asdfqwert@twitch.tv"
-            command: String::from(command),  // "PRIVMSG"
-            args,  // ["#zxcv", "arg2"]
-            trail: String::from(trail),  // "this is a message"
-            tags,  // { badges: "bits/100", display-name: "AsdfQwert", emotes: "" }
-        })
+        //  " #zxcv arg2"
+
+        if command.is_empty() {
+            return Err(IrcParseError::NoCommand);
+        }
+
+        Ok(Self { prefix, command, args, trail, tags })
+    }
+
+    /// Author: Return the name, put simply, of the source of this Message.
+    ///
+    /// Return: `&str`
+    pub fn author(&self) -> &'a str { prefix_name(self.prefix) }
+
+    /// Text: Return the trailing parameter of this Message, or an empty string
+    ///     if it has none.
+    ///
+    /// Return: `&str`
+    pub fn text(&self) -> &'a str { self.trail.unwrap_or_default() }
+
+    /// Iterate over the Arguments passed to the Command.
+    ///
+    /// Return: `impl Iterator<Item=&str>`
+    pub fn args(&self) -> impl Iterator<Item=&'a str> {
+        self.args.split_ascii_whitespace()
+    }
+
+    /// Retrieve a single Argument by its position.
+    ///
+    /// Input: `usize`
+    /// Return: `Option<&str>`
+    pub fn arg(&self, idx: usize) -> Option<&'a str> {
+        self.args().nth(idx)
+    }
+
+    /// Get a `String` representing this `MessageRef` which will identify it.
+    ///     This is the same as the [`Signature`] of the equivalent [`Message`].
+    ///
+    /// Return: [`Signature`]
+    pub fn get_signature(&self) -> Signature {
+        Signature::new(self.arg(0), Ok(self.author()))
+    }
+
+    /// Check whether this `MessageRef` includes IRC Tags.
+    ///
+    /// Return: `bool`
+    #[inline]
+    pub const fn has_tags(&self) -> bool { self.tags.is_some() }
+
+    /// Iterate over the raw key/value pairs of the IRC Tags, in their original
+    ///     order. Values are NOT unescaped, and a value is `None` if its key is
+    ///     not followed by `=`.
+    ///
+    /// Return: `impl Iterator<Item=(&str, Option<&str>)>`
+    pub fn tags(&self) -> impl Iterator<Item=(&'a str, Option<&'a str>)> {
+        self.tags.into_iter()
+            .flat_map(|tag_str| tag_str.split(';'))
+            .map(split_tag)
+    }
+
+    /// Retrieve a Tag from the `MessageRef`, without unescaping it. If a key is
+    ///     repeated, the last value is returned, as with [`Message`].
+    ///
+    /// Input: `&str`
+    /// Return: `Option<&str>`
+    pub fn get_tag_raw(&self, key: &str) -> Option<&'a str> {
+        self.tags()
+            .filter(|(k, _)| *k == key)
+            .last()
+            .map(|(_, val)| val.unwrap_or_default())
+    }
+
+    /// Retrieve a Tag from the `MessageRef`.
+    ///
+    /// Input: `&str`
+    /// Return: `Option<String>`
+    pub fn get_tag(&self, key: &str) -> Option<String> {
+        Some(unescape(self.get_tag_raw(key)?))
+    }
+
+    /// Copy this `MessageRef` into an owned [`Message`], so that it may outlive
+    ///     the line it was parsed from.
+    ///
+    /// Return: [`Message`]
+    pub fn to_message(&self) -> Message {
+        let tags: Option<Tags> = if self.has_tags() {
+            Some(self.tags().collect())
+        } else {
+            None
+        };
+
+        Message {
+            prefix: Prefix::new(self.prefix),
+            command: String::from(self.command),
+            args: self.args().map(String::from).collect(),
+            trail: self.trail.map(String::from),
+            tags,
+            args_raw: Some(self.args)
+                .filter(|raw| !is_regular_args(raw))
+                .map(String::from),
+        }
     }
 }
 
@@ -428,6 +874,282 @@ pub mod tests_irc {
         }
     }
 
+    /// Test to confirm that a borrowed `MessageRef` reads the same data as an
+    ///     owned `Message`, and converts into an identical one.
+    #[test]
+    fn test_message_ref() {
+        for init in SAMPLES {
+            let owned: Message = init.parse().expect("Failed to parse initial string.");
+            let borrowed: MessageRef = MessageRef::parse(init)
+                .expect("Failed to parse initial string.");
+
+            assert_eq!(owned.author(), borrowed.author(), "Authors differ.");
+            assert_eq!(owned.command, borrowed.command, "Commands differ.");
+            assert_eq!(owned.trail.as_deref(), borrowed.trail, "Trails differ.");
+            assert_eq!(
+                owned.args,
+                borrowed.args().collect::<Vec<_>>(),
+                "Arguments differ.",
+            );
+            assert_eq!(
+                owned.get_signature(),
+                borrowed.get_signature(),
+                "Signatures differ.",
+            );
+
+            for key in &["badges", "display-name", "emotes", TEST_KEY] {
+                assert_eq!(
+                    owned.get_tag(key),
+                    borrowed.get_tag(key),
+                    "Values for tag {key:?} differ.",
+                );
+            }
+
+            assert_eq!(
+                owned,
+                borrowed.to_message(),
+                "MessageRef DOES NOT produce an identical Message.",
+            );
+        }
+    }
+
+    /// Test to confirm that the parser agrees with the IRCv3 parser test
+    ///     vectors (`msg-split.yaml`), and that every vector is reproduced
+    ///     exactly by `Display`.
+    #[test]
+    fn test_ircv3_conformance() {
+        struct Vector {
+            line: &'static str,
+            tags: &'static [(&'static str, &'static str)],
+            source: &'static str,
+            verb: &'static str,
+            params: &'static [&'static str],
+        }
+
+        const fn v(
+            line: &'static str,
+            tags: &'static [(&'static str, &'static str)],
+            source: &'static str,
+            verb: &'static str,
+            params: &'static [&'static str],
+        ) -> Vector {
+            Vector { line, tags, source, verb, params }
+        }
+
+        let vectors: &[Vector] = &[
+            //  Simple.
+            v("foo bar baz asdf", &[], "", "foo", &["bar", "baz", "asdf"]),
+            v(":coolguy foo bar baz asdf", &[], "coolguy", "foo", &["bar", "baz", "asdf"]),
+
+            //  With trailing parameters.
+            v("foo bar baz :asdf quux", &[], "", "foo", &["bar", "baz", "asdf quux"]),
+            v("foo bar baz ::asdf", &[], "", "foo", &["bar", "baz", ":asdf"]),
+            v(":coolguy foo bar baz :asdf quux", &[], "coolguy", "foo", &["bar", "baz", "asdf quux"]),
+            v(":coolguy foo bar baz :  asdf quux ", &[], "coolguy", "foo", &["bar", "baz", "  asdf quux "]),
+            v(":coolguy PRIVMSG bar :lol :) ", &[], "coolguy", "PRIVMSG", &["bar", "lol :) "]),
+            v(":coolguy foo bar baz :  ", &[], "coolguy", "foo", &["bar", "baz", "  "]),
+            v("foo bar baz :", &[], "", "foo", &["bar", "baz", ""]),
+            v(":coolguy foo bar baz :", &[], "coolguy", "foo", &["bar", "baz", ""]),
+            v(":coolguy PRIVMSG #c :", &[], "coolguy", "PRIVMSG", &["#c", ""]),
+            v(":coolguy foo bar baz :\x01ACTION some action\x01", &[], "coolguy", "foo", &["bar", "baz", "\x01ACTION some action\x01"]),
+
+            //  With tags.
+            v("@a=b;c=32;k;rt=ql7 foo", &[("a", "b"), ("c", "32"), ("k", ""), ("rt", "ql7")], "", "foo", &[]),
+
+            //  With escaped tags.
+            v(r"@a=b\\and\nk;c=72\s45;d=gh\:764 foo", &[("a", "b\\and\nk"), ("c", "72 45"), ("d", "gh;764")], "", "foo", &[]),
+
+            //  With tags and a source.
+            v("@c;h=;a=b :quux ab cd", &[("c", ""), ("h", ""), ("a", "b")], "quux", "ab", &["cd"]),
+
+            //  Different forms of the last parameter.
+            v(":src JOIN #chan", &[], "src", "JOIN", &["#chan"]),
+            v(":src JOIN :#chan", &[], "src", "JOIN", &["#chan"]),
+
+            //  With and without a last parameter.
+            v(":src AWAY", &[], "src", "AWAY", &[]),
+            v(":src AWAY ", &[], "src", "AWAY", &[]),
+
+            //  Tab is not a delimiter in the source.
+            v(":cool\tguy foo bar baz", &[], "cool\tguy", "foo", &["bar", "baz"]),
+
+            //  Control codes in the source.
+            v(":coolguy!ag@net\x035w\x03ork.admin PRIVMSG foo :bar baz", &[], "coolguy!ag@net\x035w\x03ork.admin", "PRIVMSG", &["foo", "bar baz"]),
+            v(":coolguy!~ag@n\x02et\x0305w\x0fork.admin PRIVMSG foo :bar baz", &[], "coolguy!~ag@n\x02et\x0305w\x0fork.admin", "PRIVMSG", &["foo", "bar baz"]),
+
+            //  Tags with vendors, missing values, and empty values.
+            v("@tag1=value1;tag2;vendor1/tag3=value2;vendor2/tag4= :irc.example.com COMMAND param1 param2 :param3 param3", &[("tag1", "value1"), ("tag2", ""), ("vendor1/tag3", "value2"), ("vendor2/tag4", "")], "irc.example.com", "COMMAND", &["param1", "param2", "param3 param3"]),
+            v(":irc.example.com COMMAND param1 param2 :param3 param3", &[], "irc.example.com", "COMMAND", &["param1", "param2", "param3 param3"]),
+            v("@tag1=value1;tag2;vendor1/tag3=value2;vendor2/tag4 COMMAND param1 param2 :param3 param3", &[("tag1", "value1"), ("tag2", ""), ("vendor1/tag3", "value2"), ("vendor2/tag4", "")], "", "COMMAND", &["param1", "param2", "param3 param3"]),
+            v("COMMAND", &[], "", "COMMAND", &[]),
+
+            //  Every escape at once.
+            v(r"@foo=\\\\\:\\s\s\r\n COMMAND", &[("foo", "\\\\;\\s \r\n")], "", "COMMAND", &[]),
+
+            //  Broken messages, from real servers.
+            v(":gravel.mozilla.org 432  #momo :Erroneous Nickname: Illegal characters", &[], "gravel.mozilla.org", "432", &["#momo", "Erroneous Nickname: Illegal characters"]),
+            v(":gravel.mozilla.org MODE #tckk +n ", &[], "gravel.mozilla.org", "MODE", &["#tckk", "+n"]),
+            v(":services.esper.net MODE #foo-bar +o foobar  ", &[], "services.esper.net", "MODE", &["#foo-bar", "+o", "foobar"]),
+
+            //  Tag values which should be fixed on the receiving end.
+            v(r"@tag1=value\\ntest COMMAND", &[("tag1", "value\\ntest")], "", "COMMAND", &[]),
+            v(r"@tag1=value\1 COMMAND", &[("tag1", "value1")], "", "COMMAND", &[]),
+            v(r"@tag1=value1\ COMMAND", &[("tag1", "value1")], "", "COMMAND", &[]),
+
+            //  Duplicate keys; The last value wins.
+            v("@tag1=1;tag2=3;tag3=4;tag1=5 COMMAND", &[("tag1", "5"), ("tag2", "3"), ("tag3", "4")], "", "COMMAND", &[]),
+
+            //  Mode strings.
+            v(":SomeOp MODE #channel :+i", &[], "SomeOp", "MODE", &["#channel", "+i"]),
+            v(":SomeOp MODE #channel +oo SomeUser :AnotherUser", &[], "SomeOp", "MODE", &["#channel", "+oo", "SomeUser", "AnotherUser"]),
+        ];
+
+        for vector in vectors {
+            let line: &str = vector.line;
+            let msg: Message = line.parse()
+                .unwrap_or_else(|e| panic!("Failed to parse {line:?}: {e}"));
+            let mut params: Vec<&str> = msg.args.iter().map(String::as_str).collect();
+
+            if let Some(trail) = &msg.trail {
+                params.push(trail);
+            }
+
+            assert_eq!(vector.source, msg.prefix.to_string(), "Source of {line:?} is wrong.");
+            assert_eq!(vector.verb, msg.command, "Verb of {line:?} is wrong.");
+            assert_eq!(vector.params, params.as_slice(), "Params of {line:?} are wrong.");
+            assert_eq!(
+                vector.tags.is_empty(),
+                !msg.has_tags(),
+                "Presence of tags in {line:?} is wrong.",
+            );
+
+            for (key, value) in vector.tags {
+                assert_eq!(
+                    Some(*value),
+                    msg.get_tag(key).as_deref(),
+                    "Tag {key:?} of {line:?} is wrong.",
+                );
+            }
+
+            assert_eq!(line, msg.to_string(), "Line is not reproduced exactly.");
+        }
+
+        //  Irregular spacing is dropped once the Arguments are changed.
+        let mut msg: Message = ":services.esper.net MODE #foo-bar +o foobar  ".parse().unwrap();
+        msg.args[2] = String::from("asdf");
+        assert_eq!(msg.to_string(), ":services.esper.net MODE #foo-bar +o asdf");
+    }
+
+    /// Test to confirm that escaping and unescaping Tag values follow the IRCv3
+    ///     rules, and that Tag keys are split correctly.
+    #[test]
+    fn test_tag_escapes() {
+        let pairs: &[(&str, &str)] = &[
+            ("", ""),
+            ("a", "a"),
+            (r"\\", "\\"),
+            (r"\s", " "),
+            (r"\:", ";"),
+            (r"\r\n", "\r\n"),
+            (r"a\sb\:c\\d", "a b;c\\d"),
+        ];
+
+        for (escaped, unescaped) in pairs {
+            assert_eq!(*unescaped, unescape(escaped), "Unescape of {escaped:?} is wrong.");
+            assert_eq!(*escaped, escape(unescaped), "Escape of {unescaped:?} is wrong.");
+        }
+
+        assert_eq!("b", unescape(r"\b"));
+        assert_eq!("ab", unescape(r"ab\"));
+
+        assert_eq!(
+            Some(TagKey { client_only: true, vendor: Some("example.com"), name: "foo" }),
+            TagKey::parse("+example.com/foo"),
+        );
+        assert_eq!(
+            Some(TagKey { client_only: false, vendor: None, name: "display-name" }),
+            TagKey::parse("display-name"),
+        );
+        assert_eq!(None, TagKey::parse(""));
+        assert_eq!(None, TagKey::parse("+"));
+        assert_eq!(None, TagKey::parse("/foo"));
+        assert_eq!(None, TagKey::parse("a/b/c"));
+
+        let msg: Message = "@+draft/reply=1;id=2 :a!a@a.tmi.twitch.tv PRIVMSG #b :c"
+            .parse().expect("Failed to parse client tag sample.");
+        let tags: &Tags = msg.tags.as_ref().expect("Sample has no Tags.");
+
+        assert_eq!(
+            vec!["+draft/reply"],
+            tags.client_only().map(|tag| tag.key.as_str()).collect::<Vec<_>>(),
+        );
+    }
+
+    /// Test to confirm that built Messages are written out correctly, and can
+    ///     be read back in.
+    #[test]
+    fn test_builder() {
+        let msg: Message = MessageBuilder::new("PRIVMSG")
+            .tag("reply-parent-msg-id", "b34ccfc7-4977-403a-8a94-33c6bac34fb8")
+            .tag("client-nonce", "a b;c")
+            .arg("#zxcv")
+            .trail(":)")
+            .build();
+        let line: String = msg.to_string();
+
+        assert_eq!(
+            line,
+            r"@reply-parent-msg-id=b34ccfc7-4977-403a-8a94-33c6bac34fb8;client-nonce=a\sb\:c PRIVMSG #zxcv ::)",
+        );
+        assert_eq!(msg, line.parse().expect("Failed to parse built line."));
+        assert_eq!(msg.get_tag("client-nonce").as_deref(), Some("a b;c"));
+
+        assert_eq!(MessageBuilder::new("PING").build().to_string(), "PING");
+    }
+
+    /// Test to confirm that malformed lines are rejected with the correct
+    ///     error, rather than producing a broken Message.
+    #[test]
+    fn test_parse_errors() {
+        let too_long: String = format!(
+            ":asdf!asdf@asdf.tmi.twitch.tv PRIVMSG #zxcv :{}",
+            "a".repeat(MAX_LINE_LEN),
+        );
+        let cases: &[(&str, IrcParseError)] = &[
+            ("", IrcParseError::Empty),
+            ("   ", IrcParseError::Empty),
+            (&too_long, IrcParseError::TooLong(too_long.len())),
+            (":asdfqwert", IrcParseError::NoCommand),
+            (":asdfqwert  :trail", IrcParseError::NoCommand),
+            ("@badges=;emotes=", IrcParseError::NoCommand),
+            (": PRIVMSG #zxcv :asdf", IrcParseError::BadPrefix),
+            (":@host PRIVMSG #zxcv :asdf", IrcParseError::BadPrefix),
+            ("@ :tmi.twitch.tv PRIVMSG #zxcv :asdf", IrcParseError::BadTags),
+            ("@a=b;;c=d :tmi.twitch.tv PRIVMSG #zxcv", IrcParseError::BadTags),
+            ("@=b :tmi.twitch.tv PRIVMSG #zxcv", IrcParseError::BadTags),
+        ];
+
+        for (line, expected) in cases {
+            assert_eq!(
+                Err(*expected),
+                line.parse::<Message>(),
+                "Line {line:?} is not rejected correctly.",
+            );
+            assert_eq!(
+                Err(*expected),
+                MessageRef::parse(line),
+                "Line {line:?} is not rejected correctly by MessageRef.",
+            );
+        }
+
+        assert_eq!(Err(IrcParseError::BadPrefix), "".parse::<Prefix>());
+        assert_eq!(Err(IrcParseError::BadPrefix), "as df".parse::<Prefix>());
+        assert!(
+            "PING :tmi.twitch.tv".parse::<Message>().is_ok(),
+            "Line without a Prefix is rejected.",
+        );
+    }
+
     /// Test to confirm that manipulation of tags is working, and is preserved
     ///     across conversions between Message and text.
     #[test]
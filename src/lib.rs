@@ -7,11 +7,14 @@ extern crate hexchat;
 #[macro_use]
 mod macros;
 
+pub mod host;
+pub mod helix;
 mod ht_core;
 mod icons;
 pub mod irc;
 mod plugin;
 mod prefs;
+pub mod twitch;
 
 use plugin::HexTwitch;
 
@@ -2,22 +2,22 @@ macro_rules! ttv {($command:literal) => {concat!("SAY .", $command)}}
 
 /// Execute a command as if typed into HexChat.
 macro_rules! cmd {
-    // ($($t:tt)*) => { ::hexchat::send_command(&format!($($t)*)) };
-    ($text:literal) => { ::hexchat::send_command($text) };
-    ($f:literal, $($t:tt)*) => { ::hexchat::send_command(&format!($f, $($t)*)) };
+    // ($($t:tt)*) => { $crate::host::send_command(&format!($($t)*)) };
+    ($text:literal) => { $crate::host::send_command($text) };
+    ($f:literal, $($t:tt)*) => { $crate::host::send_command(&format!($f, $($t)*)) };
 }
 
 /// Execute a command as if typed into Twitch.
 #[allow(unused_macros)]
 macro_rules! cmd_ttv {
-    ($text:literal) => { ::hexchat::send_command(ttv!($text)) };
-    ($f:literal, $($t:tt)*) => { ::hexchat::send_command(&format!($f, $($t)*)) };
+    ($text:literal) => { $crate::host::send_command(ttv!($text)) };
+    ($f:literal, $($t:tt)*) => { $crate::host::send_command(&format!($f, $($t)*)) };
 }
 
 #[allow(unused_macros)]
 macro_rules! cmd_at {
     ($channel:expr, $($t:tt)+) => {
-        ::hexchat::send_command(&format!(
+        $crate::host::send_command(&format!(
             "DOAT {}/{} {}"
             $crate::NETWORK,
             $channel,
@@ -30,7 +30,7 @@ macro_rules! cmd_at {
 #[allow(unused_macros)]
 macro_rules! twitch_print {
     ($($t:tt)*) => {
-        ::hexchat::send_command(&format!(
+        $crate::host::send_command(&format!(
             "DOAT {} ECHO {}",
             $crate::NETWORK,
             format_args!($($t)*),
@@ -21,7 +21,7 @@ macro_rules! menu {
 }
 macro_rules! menu_add {($($t:tt)*) => {menu_run!("ADD", $($t)*)}}
 macro_rules! menu_del {($($t:tt)*) => {menu_run!("DEL", $($t)*)}}
-macro_rules! menu_run {($($t:tt)*) => {::hexchat::send_command(&menu!($($t)*))}}
+macro_rules! menu_run {($($t:tt)*) => {$crate::host::send_command(&menu!($($t)*))}}
 
 mod setup;
 mod items;
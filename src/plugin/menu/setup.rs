@@ -41,6 +41,18 @@ pub fn create_menus() -> Vec<MenuGroup> {
             cmd: "PREDICTION",
             desc: "Show channel _Prediction",
         }.with_icon(I_PREDICT));
+        twitch.add_item(MenuCommand {
+            cmd: "EMOTES",
+            desc: "Show channel _Emote usage",
+        });
+        twitch.add_item(MenuCommand {
+            cmd: "HTGIFTS",
+            desc: "Show recent _Gift recipients",
+        });
+        twitch.add_item(MenuCommand {
+            cmd: "HTSTATS",
+            desc: "Show session _Statistics",
+        });
         twitch.add_item(MenuCommand {
             cmd: "REWARD",
             desc: "Show configured _Rewards",
@@ -87,12 +99,54 @@ pub fn create_menus() -> Vec<MenuGroup> {
             set: "HTDEBUG",
             unset: None,
         });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_CHAT_LOG,
+            desc: "Log chat as JSON Lines",
+            set: "HTLOG",
+            unset: None,
+        });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_HL_EMOTES,
+            desc: "Show emotes in bold",
+            set: "HTEMOTES",
+            unset: None,
+        });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_MARK_FIRST,
+            desc: "Mark first-time chatters",
+            set: "HTFIRSTMSG",
+            unset: None,
+        });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_MARK_RETURNING,
+            desc: "Mark returning chatters",
+            set: "HTRETURNING",
+            unset: None,
+        });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_HL_NEW_CHATTERS,
+            desc: "Highlight marked chatters",
+            set: "HTNEWHIGHLIGHT",
+            unset: None,
+        });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_GIFT_LIST,
+            desc: "List gift recipients",
+            set: "HTGIFTLIST",
+            unset: None,
+        });
         twitch.add_item(MenuPrefToggle {
             pref: PREF_FOLLOW_HOSTS,
             desc: "Follow hosts",
             set: "HOSTFOLLOW",
             unset: None,
         });
+        twitch.add_item(MenuPrefToggle {
+            pref: PREF_REPLY_MENTION,
+            desc: "Keep @mention in replies",
+            set: "REPLYMENTION",
+            unset: None,
+        });
         twitch.add_item(MenuPrefToggle {
             pref: PREF_WHISPERS,
             desc: "Show whispers in current tab",
@@ -288,3 +342,30 @@ pub fn create_menus() -> Vec<MenuGroup> {
 
     menus
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::host::MockHost;
+    use super::*;
+
+    #[test]
+    fn test_menus() {
+        let host = MockHost::new("#menus").install();
+        let _ = PREF_HL_EMOTES.set(true);
+
+        let menus: Vec<MenuGroup> = create_menus();
+        let added: Vec<String> = host.take_lines();
+
+        assert!(added.iter().all(|line| line.starts_with("/MENU ")));
+        assert!(added.iter().any(|line| line.contains(r#""_Twitch/Show session _Statistics" "HTSTATS""#)));
+        assert!(added.iter().any(|line| line.starts_with("/MENU -t1 ADD") && line.contains("\"HTEMOTES\"")));
+        assert!(added.iter().any(|line| line.starts_with("/MENU -t0 ADD") && line.contains("\"HTLOG\"")));
+
+        //  Every group removes itself when it is dropped.
+        drop(menus);
+        let removed: Vec<String> = host.take_lines();
+        assert!(!removed.is_empty());
+        assert!(removed.iter().all(|line| line.starts_with("/MENU  DEL")));
+    }
+}
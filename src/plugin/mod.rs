@@ -1,8 +1,8 @@
 mod hooks;
 mod menu;
 
-use hexchat::{Plugin, print_plain, PrintEvent, WindowEvent};
-use crate::ht_core::*;
+use hexchat::{Plugin, PrintEvent, WindowEvent};
+use crate::{host::print_plain, ht_core::*};
 use hooks::{CbCommand, CbPrint, CbPrintPlugin, CbServer, CbWindow, Hook};
 use menu::*;
 
@@ -47,6 +47,7 @@ impl Plugin for HexTwitch {
     fn new() -> Self {
         crate::prefs::migrate_prefs();
         crate::prefs::init_prefs();
+        helix_check_start();
 
         let mut plugin = Self {
             hooks: Vec::with_capacity(32),
@@ -59,11 +60,70 @@ impl Plugin for HexTwitch {
             "Toggle whether Twitch Announcements should be distinctly colored.",
             cmd_pref_announce,
         );
+        plugin.hook_command(
+            "EMOTES",
+            "Display the most used Emotes in the current Twitch Channel.",
+            cmd_emotes,
+        );
+        plugin.hook_command(
+            "HTCAPTURE",
+            "Write every raw line received from Twitch to a file, to be \
+            attached to a bug report. Filters may be given as IRC Commands or \
+            `msg-id` values, separated by commas. With `redact`, OAuth tokens \
+            and your own username are removed from each line. If no path is \
+            given, a new file is created in the HexChat config directory.\n\n\
+                Usage: HTCAPTURE start [filter:<command|msg-id>] [redact] [<path>]\n\
+                Usage: HTCAPTURE stop\n\
+                Usage: HTCAPTURE status",
+            cmd_htcapture,
+        );
         plugin.hook_command(
             "HTDEBUG",
             "Toggle whether extra debug information should be printed.",
             cmd_pref_debug,
         );
+        plugin.hook_command(
+            "HTEMOTES",
+            "Toggle whether Emotes should be shown in bold.",
+            cmd_pref_hl_emotes,
+        );
+        plugin.hook_command(
+            "HTFIRSTMSG",
+            "Toggle whether the first message of a new chatter should be marked.",
+            cmd_pref_mark_first,
+        );
+        plugin.hook_command(
+            "HTRETURNING",
+            "Toggle whether messages from returning chatters should be marked.",
+            cmd_pref_mark_returning,
+        );
+        plugin.hook_command(
+            "HTNEWHIGHLIGHT",
+            "Toggle whether marked first-time and returning chatters should \
+            highlight the Tab.",
+            cmd_pref_hl_new_chatters,
+        );
+        plugin.hook_command(
+            "HTGIFTS",
+            "List the recipients of a set of random gift subscriptions. Lists \
+            the most recent set in the current Channel if no ID is given.\n\n\
+                Usage: HTGIFTS [<origin-id>]",
+            cmd_htgifts,
+        );
+        plugin.hook_command(
+            "HTGIFTLIST",
+            "Toggle whether the recipients of random gift subscriptions should \
+            be listed automatically.",
+            cmd_pref_gift_list,
+        );
+        plugin.hook_command(
+            "HTLOG",
+            "Toggle whether every line in Twitch Channels should be logged, \
+            with its Tags, as JSON Lines. Logs are kept in the HexChat config \
+            directory, under `hextwitch/logs`, with one file per Channel per \
+            day.",
+            cmd_pref_chat_log,
+        );
         plugin.hook_command(
             "HTMODES",
             "Automatically assign fake IRC modes to channel moderators.",
@@ -74,6 +134,48 @@ impl Plugin for HexTwitch {
             "Print information about the HexTwitch plugin.",
             cmd_htinfo,
         );
+        plugin.hook_command(
+            "HTSEARCH",
+            "Search the recent chat history of a Twitch Channel. Searches the \
+            current Channel if no other is given. The pattern is a regular \
+            expression, matched against the text of each message.\n\n\
+                Usage: HTSEARCH [#channel] [user:<login>] [badge:<name>] <regex>",
+            cmd_htsearch,
+        );
+        plugin.hook_command(
+            "HTAUTH",
+            "Set the Client ID and OAuth token used for requests to the Twitch \
+            Helix API, and check that they are valid. If a refresh token is \
+            given, it is used to replace the OAuth token when it expires; This \
+            only works for a token issued to a public client, because no \
+            client secret is sent. With no arguments, checks the token already \
+            set.\n\n\
+            Tokens typed here are kept in the input history. To avoid this, \
+            give only the Client ID, and enter the tokens in the prompt that \
+            follows. Either way, they are stored in the HexChat preferences \
+            file, in plain text.\n\n\
+                Usage: HTAUTH [<client-id> [<token> [<refresh-token>]] | clear]",
+            cmd_htauth,
+        );
+        plugin.hook_command(
+            "HTQUEUE",
+            "Show the messages waiting to be sent in the current Twitch \
+            Channel because of slow mode or the rate limit, or cancel them. \
+            Messages typed before the wait is over are held, and sent once \
+            it is.\n\n\
+                Usage: HTQUEUE [clear]",
+            cmd_htqueue,
+        );
+        plugin.hook_command(
+            "HTSTATS",
+            "Show the subscriptions, gifts, Bits and raids seen in a Twitch \
+            Channel during this session, reset them, or export every event \
+            to a file. Uses the current Channel if no other is given. Exports \
+            are written as JSON Lines if the path ends in `.json` or `.jsonl`, \
+            and as CSV otherwise.\n\n\
+                Usage: HTSTATS [#channel] [reset | export <path>]",
+            cmd_htstats,
+        );
         plugin.hook_command(
             "HOSTFOLLOW",
             "Toggle whether Twitch Hosts will be followed through to the target channel.",
@@ -84,6 +186,21 @@ impl Plugin for HexTwitch {
             "View the current Prediction of the current Twitch Channel.",
             cmd_prediction,
         );
+        plugin.hook_command(
+            "REPLY",
+            "Reply to a message in the current Twitch Channel. The message may \
+            be given by its ID, or by the name of the User who sent it, in \
+            which case their most recent message is used. In slow mode, or \
+            at the rate limit, the reply waits in the queue.\n\n\
+                Usage: REPLY <msg-id|nick> <text>",
+            cmd_reply,
+        );
+        plugin.hook_command(
+            "REPLYMENTION",
+            "Toggle whether Replies should keep the @mention of the User they \
+            reply to.",
+            cmd_pref_reply_mention,
+        );
         plugin.hook_command(
             "REWARD",
             "Set the Name of a Custom Reward.\n\n\
@@ -115,10 +232,13 @@ impl Plugin for HexTwitch {
         );
         plugin.hook_command(
             "UNKNOWNS",
-            "Display unknown Badge Keys that have been seen.",
+            "Display unknown Badge Keys and NOTICE IDs that have been seen.",
             cmd_unk_badges,
         );
 
+        //  Hook for plain text typed by the user.
+        plugin.hook_command("", "", cb_say);
+
         //  Hook for Server Notices.
         plugin.hook_print(PrintEvent::SERVER_NOTICE, cb_notice);
 
@@ -32,6 +32,11 @@ const PREFIX: &'static str = pref!();
 pub const PREF_ANNOUNCE: PrefBool = PrefBool::new(pref!("color_announcements"));
 
 
+/// Preference: Whether every line in a Twitch Channel should be written, with
+///     its Tags, to a JSON Lines log.
+pub const PREF_CHAT_LOG: PrefBool = PrefBool::new(pref!("log_jsonl"));
+
+
 /// Preference: Debug mode for the plugin.
 pub const PREF_DEBUG: PrefMigrating<PrefBool> = PrefMigrating {
     new: PrefBool::new(pref!("debug")),
@@ -39,9 +44,51 @@ pub const PREF_DEBUG: PrefMigrating<PrefBool> = PrefMigrating {
 };
 
 
+/// Preference: Whether the first message a User ever sends in a channel should
+///     be marked.
+pub const PREF_MARK_FIRST: PrefBool = PrefBool::new(pref!("mark_first_msg"));
+
+
+/// Preference: Whether messages from returning chatters should be marked.
+pub const PREF_MARK_RETURNING: PrefBool = PrefBool::new(pref!("mark_returning"));
+
+
+/// Preference: Whether marked first and returning messages should also color
+///     the tab as a highlight.
+pub const PREF_HL_NEW_CHATTERS: PrefBool = PrefBool::new(pref!("highlight_new_chatters"));
+
+
 pub const PREF_FOLLOW_HOSTS: PrefBool = PrefBool::new(pref!("follow_hosts"));
 
 
+/// Preference: Whether the recipients of a set of random gift subscriptions
+///     should be listed automatically, shortly after the gifts are given.
+pub const PREF_GIFT_LIST: PrefBool = PrefBool::new(pref!("list_gift_recipients"));
+
+
+/// Preference: Client ID of the application used to make requests to the Twitch
+///     Helix API.
+pub const PREF_HELIX_CLIENT_ID: PrefStr = PrefStr::new(pref!("helix_client_id"));
+
+
+/// Preference: OAuth token for the Twitch Helix API.
+pub const PREF_HELIX_TOKEN: PrefStr = PrefStr::new(pref!("helix_token"));
+
+
+/// Preference: Token used to get a new OAuth token for the Twitch Helix API,
+///     when the current one expires.
+pub const PREF_HELIX_REFRESH: PrefStr = PrefStr::new(pref!("helix_refresh"));
+
+
+/// Preference: Whether Emotes should be shown in bold in chat messages.
+pub const PREF_HL_EMOTES: PrefBool = PrefBool::new(pref!("highlight_emotes"));
+
+
+/// Preference: Whether the `@parent` mention at the start of a reply should be
+///     kept, rather than removed in favor of a quote of the parent message.
+pub const PREF_REPLY_MENTION: PrefBool = PrefBool::new(pref!("reply_keep_mention"));
+
+
 /// Preference: Whether incoming whispers should be displayed in the current
 ///     channel in addition to their respective tabs.
 pub const PREF_WHISPERS: PrefBool = PrefBool::new(pref!("whispers_in_current"));
@@ -51,7 +98,7 @@ pub const PREF_WHISPERS: PrefBool = PrefBool::new(pref!("whispers_in_current"));
 pub fn init_prefs() {
     fn init_report<T>(pref: impl HexPrefGet + HexPrefSet<T>, value: T) {
         if let Err(()) = pref.init(value) {
-            hexchat::print_plain(&format!(
+            crate::host::print_plain(&format!(
                 "Failed to set initial value for preference: {}",
                 pref.name(),
             ));
@@ -59,8 +106,15 @@ pub fn init_prefs() {
     }
 
     init_report(PREF_ANNOUNCE, true);
+    init_report(PREF_CHAT_LOG, false);
     init_report(PREF_DEBUG, false);
     init_report(PREF_FOLLOW_HOSTS, false);
+    init_report(PREF_GIFT_LIST, false);
+    init_report(PREF_HL_EMOTES, false);
+    init_report(PREF_HL_NEW_CHATTERS, false);
+    init_report(PREF_MARK_FIRST, true);
+    init_report(PREF_MARK_RETURNING, true);
+    init_report(PREF_REPLY_MENTION, false);
     init_report(PREF_WHISPERS, false);
 }
 
@@ -95,9 +149,41 @@ pub fn migrate_prefs() {
         };
 
         if let Some(text) = report {
-            hexchat::print_plain(&text);
+            crate::host::print_plain(&text);
         }
     }
 
     migrate_report(PREF_DEBUG);
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::host::MockHost;
+    use super::*;
+
+    #[test]
+    fn test_prefs() {
+        let host = MockHost::new("#prefs").install();
+
+        init_prefs();
+        assert!(PREF_ANNOUNCE.is(&true));
+        assert!(PREF_HL_EMOTES.is(&false));
+        assert_eq!(host.prefs.borrow().get(PREF_ANNOUNCE.name()).unwrap(), "1");
+
+        assert_eq!(PREF_HL_EMOTES.toggle(), Ok(true));
+        assert!(PREF_HL_EMOTES.is(&true));
+
+        //  Values that are already set must not be reset.
+        init_prefs();
+        assert!(PREF_HL_EMOTES.is(&true));
+
+        //  An old preference is moved to its new name.
+        host.prefs.borrow_mut().insert(String::from("PREF_htdebug"), String::from("1"));
+        host.prefs.borrow_mut().remove(PREF_DEBUG.new.name());
+        migrate_prefs();
+        assert!(PREF_DEBUG.new.is(&true));
+        assert!(PREF_DEBUG.old.get().is_none());
+        assert_eq!(host.take().len(), 1);
+    }
+}
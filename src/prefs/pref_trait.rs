@@ -108,6 +108,6 @@ pub trait HexPrefSet<Input>: HexPref {
 pub trait HexPrefUnset: HexPref {
     /// Unset this preference, leaving it with no value.
     fn unset(&self) -> Result<(), ()> {
-        hexchat::delete_pref(self.name())
+        crate::host::delete_pref(self.name())
     }
 }
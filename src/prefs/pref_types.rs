@@ -23,7 +23,7 @@ impl HexPrefGet for PrefBool {
     type Output = bool;
 
     fn get(&self) -> Option<Self::Output> {
-        let n: u32 = hexchat::get_pref_int(self.name())?;
+        let n: u32 = crate::host::get_pref_int(self.name())?;
 
         Some(n != 0)
     }
@@ -31,7 +31,7 @@ impl HexPrefGet for PrefBool {
 
 impl HexPrefSet<bool> for PrefBool {
     fn set(&self, value: bool) -> Result<(), ()> {
-        hexchat::set_pref_int(self.name(), value as _)
+        crate::host::set_pref_int(self.name(), value as _)
     }
 }
 
@@ -56,13 +56,13 @@ impl HexPrefGet for PrefInt {
     type Output = u32;
 
     fn get(&self) -> Option<Self::Output> {
-        hexchat::get_pref_int(self.name())
+        crate::host::get_pref_int(self.name())
     }
 }
 
 impl HexPrefSet<u32> for PrefInt {
     fn set(&self, value: u32) -> Result<(), ()> {
-        hexchat::set_pref_int(self.name(), value)
+        crate::host::set_pref_int(self.name(), value)
     }
 }
 
@@ -73,7 +73,6 @@ impl HexPrefUnset for PrefInt {}
 pub struct PrefStr(&'static str);
 
 impl PrefStr {
-    #[allow(dead_code)]
     pub(super) const fn new(name: &'static str) -> Self {
         Self(name)
     }
@@ -87,13 +86,13 @@ impl HexPrefGet for PrefStr {
     type Output = String;
 
     fn get(&self) -> Option<Self::Output> {
-        hexchat::get_pref_string(self.name())
+        crate::host::get_pref_string(self.name())
     }
 }
 
 impl<T: AsRef<str>> HexPrefSet<T> for PrefStr {
     fn set(&self, value: T) -> Result<(), ()> {
-        hexchat::set_pref_string(self.name(), value.as_ref())
+        crate::host::set_pref_string(self.name(), value.as_ref())
     }
 }
 
@@ -49,7 +49,7 @@ impl Reward {
     }
 
     pub fn get_all() -> impl Iterator<Item=Self> {
-        hexchat::get_prefs().into_iter().filter_map(Self::new)
+        crate::host::get_prefs().into_iter().filter_map(Self::new)
     }
 
     pub fn id(&self) -> &str { &self.name() }
@@ -75,13 +75,13 @@ impl HexPrefGet for Reward {
     type Output = String;
 
     fn get(&self) -> Option<Self::Output> {
-        hexchat::get_pref_string(self.name())
+        crate::host::get_pref_string(self.name())
     }
 }
 
 impl<T: AsRef<str>> HexPrefSet<T> for Reward {
     fn set(&self, value: T) -> Result<(), ()> {
-        hexchat::set_pref_string(self.name(), value.as_ref())
+        crate::host::set_pref_string(self.name(), value.as_ref())
     }
 }
 
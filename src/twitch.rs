@@ -0,0 +1,1090 @@
+//! Module for the decoding of IRC [`Message`]s into typed Twitch events.
+//!
+//! Nothing here prints anything or depends on HexChat; Decoding a Message and
+//!     rendering it are separate steps, and only the first is done here.
+
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt::{Display, Formatter},
+    ops::Range,
+    str::FromStr,
+};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::Serialize;
+use crate::irc::Message;
+
+
+/// The numeric ID of a Twitch User. A Channel also uses the ID of its owner.
+pub type UserId = u64;
+
+
+/// A reason that a [`Message`] could not be decoded into a [`TwitchEvent`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    /// The IRC Command does not correspond to a Twitch event.
+    UnknownCommand,
+    /// The Message has no Channel argument.
+    MissingChannel,
+    /// A Tag required by the event is not present.
+    MissingTag(&'static str),
+    /// A Tag required by the event is present, but could not be interpreted.
+    BadTag(&'static str),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownCommand => f.write_str("command is not a Twitch event"),
+            Self::MissingChannel => f.write_str("channel is missing"),
+            Self::MissingTag(key) => write!(f, "tag {:?} is missing", key),
+            Self::BadTag(key) => write!(f, "tag {:?} is invalid", key),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+
+/// Retrieve a Tag, but only if it is not empty.
+fn tag(msg: &Message, key: &str) -> Option<String> {
+    msg.get_tag(key).filter(|val| !val.is_empty())
+}
+
+
+/// Retrieve a Tag which is a flag, set to `1` when it applies.
+fn tag_flag(msg: &Message, key: &str) -> bool {
+    msg.get_tag(key).as_deref() == Some("1")
+}
+
+
+/// Retrieve a Tag which is required for an event to make sense.
+fn tag_req(msg: &Message, key: &'static str) -> Result<String, DecodeError> {
+    tag(msg, key).ok_or(DecodeError::MissingTag(key))
+}
+
+
+/// Retrieve a Tag and parse it into some other type. Values that cannot be
+///     parsed are treated the same as missing values.
+fn tag_parse<T: FromStr>(msg: &Message, key: &str) -> Option<T> {
+    tag(msg, key)?.parse().ok()
+}
+
+
+/// Retrieve a Tag which is required for an event to make sense, and parse it
+///     into some other type.
+fn tag_parse_req<T: FromStr>(msg: &Message, key: &'static str)
+    -> Result<T, DecodeError>
+{
+    tag_req(msg, key)?.parse().or(Err(DecodeError::BadTag(key)))
+}
+
+
+/// Convert a Twitch timestamp, in milliseconds since the Unix epoch, into a
+///     `DateTime`.
+pub fn timestamp(millis: &str) -> Option<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(millis.parse().ok()?).single()
+}
+
+
+/// The subscription plan of a User, as given by `msg-param-sub-plan`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubPlan {
+    Prime,
+    Tier1,
+    Tier2,
+    Tier3,
+    /// A plan not known to the plugin. Contains the original value.
+    Other(String),
+}
+
+impl SubPlan {
+    /// Numeric tier of this plan. Prime subscriptions are at Tier 1.
+    pub const fn tier(&self) -> Option<u8> {
+        match self {
+            Self::Prime | Self::Tier1 => Some(1),
+            Self::Tier2 => Some(2),
+            Self::Tier3 => Some(3),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl FromStr for SubPlan {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err(()),
+            "Prime" => Ok(Self::Prime),
+            "1000" => Ok(Self::Tier1),
+            "2000" => Ok(Self::Tier2),
+            "3000" => Ok(Self::Tier3),
+            other => Ok(Self::Other(other.to_owned())),
+        }
+    }
+}
+
+
+/// A Tag which should hold a number, but might not. A value that cannot be
+///     parsed is kept in its original form, so that it can still be shown.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Number<T> {
+    Valid(T),
+    /// A value which could not be parsed. Contains the original value.
+    Other(String),
+}
+
+impl<T: Copy> Number<T> {
+    /// The parsed value, if there is one.
+    pub fn valid(&self) -> Option<T> {
+        match self {
+            Self::Valid(n) => Some(*n),
+            Self::Other(_) => None,
+        }
+    }
+}
+
+impl<T: Display> Display for Number<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Valid(n) => n.fmt(f),
+            Self::Other(s) => f.write_str(s),
+        }
+    }
+}
+
+impl<T: FromStr> FromStr for Number<T> {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err(()),
+            s => Ok(s.parse().map_or_else(|_| Self::Other(s.to_owned()), Self::Valid)),
+        }
+    }
+}
+
+
+/// The reason for a NOTICE from Twitch, as given by its `msg-id` Tag.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeId {
+    /// The message was identical to one sent just before it.
+    Duplicate,
+    /// The Channel is in slow mode, and the last message was too recent.
+    SlowMode,
+    /// The Channel is in Followers-Only mode.
+    FollowersOnly,
+    /// The Channel is in Subscribers-Only mode.
+    SubsOnly,
+    /// The Channel is in Emote-Only mode.
+    EmoteOnly,
+    /// The Channel is in Unique mode, and the message was not unique.
+    Unique,
+    /// The User is banned from the Channel.
+    Banned,
+    /// The User is timed out in the Channel.
+    TimedOut,
+    /// Too many messages have been sent too quickly.
+    RateLimit,
+    /// The Channel only allows Users with verified phone numbers to chat.
+    RequiresVerifiedPhone,
+    /// A chat command was not recognized.
+    UnrecognizedCmd,
+    /// The list of moderators, in response to a command. These are handled by
+    ///     Callbacks, and are not explained.
+    Mods,
+    /// An ID not known to the plugin. Contains the original value.
+    Other(String),
+}
+
+impl FromStr for NoticeId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" => Err(()),
+            "msg_duplicate" => Ok(Self::Duplicate),
+            "msg_slowmode" => Ok(Self::SlowMode),
+            "msg_followersonly"
+            | "msg_followersonly_followed"
+            | "msg_followersonly_zero" => Ok(Self::FollowersOnly),
+            "msg_subsonly" => Ok(Self::SubsOnly),
+            "msg_emoteonly" => Ok(Self::EmoteOnly),
+            "msg_r9k" => Ok(Self::Unique),
+            "msg_banned" => Ok(Self::Banned),
+            "msg_timedout" => Ok(Self::TimedOut),
+            "msg_ratelimit" => Ok(Self::RateLimit),
+            "msg_requires_verified_phone_number" => Ok(Self::RequiresVerifiedPhone),
+            "unrecognized_cmd" => Ok(Self::UnrecognizedCmd),
+            "room_mods" | "no_mods" => Ok(Self::Mods),
+            other => Ok(Self::Other(other.to_owned())),
+        }
+    }
+}
+
+
+/// A single use of an Emote within the text of a Message.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct EmoteSpan {
+    /// ID of the Emote. Usually numeric, but not always.
+    pub id: String,
+    /// Position of the Emote in the text, counted in Unicode code points
+    ///     rather than bytes. Unlike in the Tag itself, the end is exclusive.
+    pub chars: Range<usize>,
+}
+
+
+/// The Emotes used in a Message, as given by the `emotes` Tag.
+///
+/// The Tag lists each Emote once, with every position where it is used, as in
+///     `25:0-4,12-16/1902:6-10`. Here, the positions are flattened out and kept
+///     in the order they appear in the text.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct Emotes(Vec<EmoteSpan>);
+
+impl Emotes {
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+    pub fn len(&self) -> usize { self.0.len() }
+
+    /// Iterate over the Emote spans, in the order they appear in the text.
+    pub fn iter(&self) -> impl Iterator<Item=&EmoteSpan> { self.0.iter() }
+
+    /// Map the Emote spans onto the text of their Message, yielding each span
+    ///     along with the byte range and the text it covers. Spans that do not
+    ///     fit within the text are skipped.
+    ///
+    /// Input: `&str`
+    /// Return: `impl Iterator<Item=(&EmoteSpan, Range<usize>, &str)>`
+    pub fn locate<'e, 't: 'e>(&'e self, text: &'t str)
+        -> impl Iterator<Item=(&'e EmoteSpan, Range<usize>, &'t str)> + 'e
+    {
+        //  Byte offset of every code point, plus the end of the text, so that
+        //      an exclusive end can also be looked up.
+        let offsets: Vec<usize> = text.char_indices()
+            .map(|(idx, _)| idx)
+            .chain(std::iter::once(text.len()))
+            .collect();
+
+        self.0.iter().filter_map(move |span| {
+            let start: usize = *offsets.get(span.chars.start)?;
+            let end: usize = *offsets.get(span.chars.end)?;
+
+            Some((span, start..end, &text[start..end]))
+        })
+    }
+
+    /// Wrap every Emote in a text with formatting codes. The `before` string is
+    ///     inserted at the start of each Emote, and `after` at its end.
+    ///
+    /// Input: `&str`, `&str`, `&str`
+    /// Return: `String`
+    pub fn highlight(&self, text: &str, before: &str, after: &str) -> String {
+        let mut out = String::with_capacity(
+            text.len() + self.len() * (before.len() + after.len()),
+        );
+        let mut done: usize = 0;
+
+        for (_, bytes, word) in self.locate(text) {
+            //  Overlapping spans should never be sent, but if they are, the
+            //      later one is ignored.
+            if bytes.start < done { continue; }
+
+            out.push_str(&text[done..bytes.start]);
+            out.push_str(before);
+            out.push_str(word);
+            out.push_str(after);
+            done = bytes.end;
+        }
+
+        out.push_str(&text[done..]);
+        out
+    }
+}
+
+impl FromStr for Emotes {
+    type Err = DecodeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERR: DecodeError = DecodeError::BadTag("emotes");
+        let mut spans: Vec<EmoteSpan> = Vec::new();
+
+        for emote in s.split('/').filter(|e| !e.is_empty()) {
+            let (id, positions) = emote.split_once(':').ok_or(ERR)?;
+
+            for pos in positions.split(',') {
+                let (first, last) = pos.split_once('-').ok_or(ERR)?;
+                let first: usize = first.parse().or(Err(ERR))?;
+                let last: usize = last.parse().or(Err(ERR))?;
+
+                if last < first || id.is_empty() {
+                    return Err(ERR);
+                }
+
+                spans.push(EmoteSpan {
+                    id: id.to_owned(),
+                    chars: first..last.checked_add(1).ok_or(ERR)?,
+                });
+            }
+        }
+
+        spans.sort_unstable_by_key(|span| span.chars.start);
+        Ok(Self(spans))
+    }
+}
+
+
+/// The message that a chat message was sent in reply to, as given by the
+///     `reply-parent-*` Tags.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ReplyParent {
+    pub msg_id: String,
+    pub login: String,
+    pub display_name: Option<String>,
+    pub body: String,
+}
+
+impl ReplyParent {
+    /// Read the parent of a Message. Returns `None` if the Message is not a
+    ///     reply.
+    ///
+    /// Input: `&Message`
+    /// Return: `Option<ReplyParent>`
+    pub fn from_message(msg: &Message) -> Option<Self> {
+        Some(Self {
+            msg_id: tag(msg, "reply-parent-msg-id")?,
+            login: tag(msg, "reply-parent-user-login")?,
+            display_name: tag(msg, "reply-parent-display-name"),
+            body: msg.get_tag("reply-parent-msg-body").unwrap_or_default(),
+        })
+    }
+
+    /// Check whether this is a reply to a message from a given User.
+    pub fn is_to(&self, login: &str) -> bool {
+        self.login.eq_ignore_ascii_case(login)
+    }
+
+    /// Render a short quote of the parent, such as `↪ @user: first words…`.
+    ///     The body is cut off after a number of characters.
+    ///
+    /// Input: `usize`
+    /// Return: `String`
+    pub fn quote(&self, max_chars: usize) -> String {
+        let name: &str = self.display_name.as_deref().unwrap_or(&self.login);
+        let body: &str = self.body.trim();
+
+        match body.char_indices().nth(max_chars) {
+            Some((cut, _)) => format!("↪ @{}: {}…", name, body[..cut].trim_end()),
+            None => format!("↪ @{}: {}", name, body),
+        }
+    }
+
+    /// Remove the `@parent` mention that Twitch puts at the start of a reply.
+    ///     If the text does not start with the mention, it is returned as is.
+    ///
+    /// Input: `&str`
+    /// Return: `&str`
+    pub fn strip_mention<'t>(&self, text: &'t str) -> &'t str {
+        let names = std::iter::once(self.login.as_str())
+            .chain(self.display_name.as_deref());
+
+        for name in names {
+            let rest: &str = match text.strip_prefix('@') {
+                Some(rest) if matches!(
+                    rest.get(..name.len()),
+                    Some(head) if head.eq_ignore_ascii_case(name),
+                ) => &rest[name.len()..],
+                _ => continue,
+            };
+
+            //  Make sure the whole name was matched, and not just the start.
+            if rest.is_empty() || rest.starts_with(' ') {
+                return rest.trim_start();
+            }
+        }
+
+        text
+    }
+}
+
+
+/// A Twitch User, as identified by the Tags of a Message.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct User {
+    /// Lowercase login name.
+    pub login: String,
+    /// Name as chosen by the User. May differ from the login in case, or in
+    ///     its entirety for non-Latin names.
+    pub display_name: Option<String>,
+    /// Numeric ID.
+    pub id: Option<UserId>,
+}
+
+impl User {
+    /// Read the sender of a Message. The login is taken from the `login` Tag if
+    ///     it is present, and the Prefix otherwise.
+    fn sender(msg: &Message) -> Result<Self, DecodeError> {
+        let login: String = match tag(msg, "login") {
+            Some(login) => login,
+            None if !msg.author().is_empty() && !msg.author().contains('.') =>
+                msg.author().to_owned(),
+            None => return Err(DecodeError::MissingTag("login")),
+        };
+
+        Ok(Self {
+            login,
+            display_name: tag(msg, "display-name"),
+            id: tag_parse(msg, "user-id"),
+        })
+    }
+
+    /// Read a User from a group of Tags sharing a prefix, such as the
+    ///     recipient of a gift in `msg-param-recipient-user-name`.
+    fn from_params(
+        msg: &Message,
+        key_login: &'static str,
+        key_display: &str,
+        key_id: &str,
+    ) -> Result<Self, DecodeError> {
+        Ok(Self {
+            login: tag_req(msg, key_login)?,
+            display_name: tag(msg, key_display),
+            id: tag_parse(msg, key_id),
+        })
+    }
+}
+
+
+/// Data common to all Twitch events.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct EventMeta {
+    /// The Channel in which the event occurred, including its `#`. For a
+    ///     Whisper, this is instead the name of the recipient.
+    pub channel: String,
+    /// Unique ID of the Message, from the `id` Tag.
+    pub id: Option<String>,
+    /// ID of the Channel, from the `room-id` Tag.
+    pub room_id: Option<UserId>,
+    /// Time at which the Message was sent, from the `tmi-sent-ts` Tag.
+    pub sent: Option<DateTime<Utc>>,
+}
+
+impl EventMeta {
+    fn new(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self {
+            channel: msg.args.first().ok_or(DecodeError::MissingChannel)?.clone(),
+            id: tag(msg, "id"),
+            room_id: tag_parse(msg, "room-id"),
+            sent: msg.get_tag("tmi-sent-ts").as_deref().and_then(timestamp),
+        })
+    }
+}
+
+
+/// Details of a paid subscription, shared by several event types.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct SubDetails {
+    pub plan: Option<SubPlan>,
+    /// Number of consecutive months subscribed, if it is shared.
+    pub streak_months: Option<u32>,
+    /// Number of months subscribed in total.
+    pub cumulative_months: Option<u32>,
+}
+
+impl SubDetails {
+    fn new(msg: &Message) -> Self {
+        Self {
+            plan: tag_parse(msg, "msg-param-sub-plan"),
+            streak_months: tag_parse(msg, "msg-param-streak-months"),
+            cumulative_months: tag_parse(msg, "msg-param-cumulative-months"),
+        }
+    }
+}
+
+
+/// The source of a subscription being upgraded from a gift or from Prime.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Upgrade {
+    /// Continuing a gift subscription from a named User.
+    Gift { sender: String },
+    /// Continuing a gift subscription from an anonymous User.
+    AnonGift,
+    /// Converting a Prime subscription into a paid one.
+    Prime,
+}
+
+
+/// An event on Twitch, decoded from an IRC [`Message`].
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TwitchEvent {
+    /// A chat message in a Channel.
+    Privmsg {
+        meta: EventMeta,
+        user: User,
+        text: String,
+        badges: String,
+        badge_info: String,
+        emotes: Emotes,
+        reply: Option<ReplyParent>,
+        bits: Option<u32>,
+        /// ID of the Channel Points Reward redeemed with this message.
+        reward_id: Option<String>,
+        /// Whether this message was highlighted with Channel Points.
+        highlighted: bool,
+        /// Whether this is the first message the User has ever sent in the
+        ///     Channel.
+        first_msg: bool,
+        /// Whether the User has chatted before, but not recently.
+        returning: bool,
+    },
+    /// A private message.
+    Whisper {
+        meta: EventMeta,
+        user: User,
+        text: String,
+        thread_id: Option<String>,
+    },
+
+    /// A first subscription to a Channel.
+    Sub { meta: EventMeta, user: User, sub: SubDetails, text: Option<String> },
+    /// A renewed subscription to a Channel.
+    Resub { meta: EventMeta, user: User, sub: SubDetails, text: Option<String> },
+    /// A subscription extended in advance.
+    ExtendSub {
+        meta: EventMeta,
+        user: User,
+        sub: SubDetails,
+        /// Number of the month, from 1 to 12, in which the extension ends.
+        end_month: Option<Number<u8>>,
+        text: Option<String>,
+    },
+    /// A subscription given to a specific User.
+    SubGift {
+        meta: EventMeta,
+        user: User,
+        recipient: User,
+        plan: Option<SubPlan>,
+        /// Number of consecutive months the recipient has been subscribed.
+        months: Option<u32>,
+        /// Number of months the recipient has been subscribed in total.
+        cumulative_months: Option<u32>,
+        /// Total number of gifts the sender has given in this Channel.
+        sender_count: Option<u32>,
+        /// Shared ID of a group of gifts given together.
+        origin_id: Option<String>,
+    },
+    /// Subscriptions given to random Users in a Channel.
+    MysteryGift {
+        meta: EventMeta,
+        user: User,
+        count: u32,
+        plan: Option<SubPlan>,
+        /// Total number of gifts the sender has given in this Channel.
+        sender_count: Option<u32>,
+        /// Shared ID of a group of gifts given together.
+        origin_id: Option<String>,
+    },
+    /// A gift subscription being paid forward.
+    PayForward {
+        meta: EventMeta,
+        user: User,
+        /// Login of the User who originally gave a gift. `None` if anonymous.
+        prior_gifter: Option<String>,
+        /// Login of the User receiving a gift. `None` if given to the community.
+        recipient: Option<String>,
+    },
+    /// A subscription upgraded from a gift or from Prime.
+    SubUpgrade { meta: EventMeta, user: User, from: Upgrade },
+
+    /// A raid incoming from another Channel.
+    Raid { meta: EventMeta, user: User, from: String, viewers: Number<u32> },
+    /// A raid being canceled.
+    Unraid { meta: EventMeta },
+    /// A User reaching a new tier of Bits Badge.
+    BitsBadgeTier {
+        meta: EventMeta,
+        user: User,
+        threshold: Option<u32>,
+        text: Option<String>,
+    },
+    /// A highlighted message sent by a Moderator.
+    Announcement {
+        meta: EventMeta,
+        user: User,
+        color: Option<String>,
+        text: String,
+    },
+    /// A USERNOTICE of a known type which is only represented by its system
+    ///     message, such as a `ritual`.
+    SystemMessage { meta: EventMeta, msg_id: String, text: String },
+    /// A USERNOTICE of an unknown type.
+    UnknownNotice { meta: EventMeta, msg_id: String, system_msg: Option<String> },
+
+    /// Chat history being cleared, or a User being banned or timed out.
+    ClearChat {
+        meta: EventMeta,
+        /// Login of the User being banned. `None` if all chat is cleared.
+        target: Option<String>,
+        target_id: Option<UserId>,
+        /// Length of the timeout in seconds. `None` if the ban is permanent.
+        duration: Option<u32>,
+        reason: Option<String>,
+    },
+    /// A single message being deleted.
+    ClearMsg {
+        meta: EventMeta,
+        login: String,
+        target_msg_id: Option<String>,
+        text: String,
+    },
+
+    /// A change to the settings of a Channel. Only the settings which are
+    ///     included in the Message are `Some`.
+    RoomState {
+        meta: EventMeta,
+        emote_only: Option<bool>,
+        /// Minutes a User must follow before chatting. Negative if disabled.
+        followers_only: Option<i32>,
+        unique: Option<bool>,
+        /// Seconds a User must wait between messages. Zero if disabled.
+        slow: Option<u32>,
+        subs_only: Option<bool>,
+    },
+    /// The state of our own User in a Channel.
+    UserState {
+        meta: EventMeta,
+        badges: String,
+        badge_info: String,
+        display_name: Option<String>,
+    },
+    /// A Channel hosting another.
+    HostTarget {
+        meta: EventMeta,
+        /// Channel being hosted. `None` if hosting has stopped.
+        target: Option<String>,
+        viewers: Option<u32>,
+    },
+    /// The server is about to restart.
+    Reconnect,
+    /// A message from the server.
+    Notice { meta: EventMeta, msg_id: Option<NoticeId>, text: String },
+}
+
+impl TwitchEvent {
+    /// Get the common data of this event, if it has any.
+    pub const fn meta(&self) -> Option<&EventMeta> {
+        match self {
+            Self::Privmsg { meta, .. }
+            | Self::Whisper { meta, .. }
+            | Self::Sub { meta, .. }
+            | Self::Resub { meta, .. }
+            | Self::ExtendSub { meta, .. }
+            | Self::SubGift { meta, .. }
+            | Self::MysteryGift { meta, .. }
+            | Self::PayForward { meta, .. }
+            | Self::SubUpgrade { meta, .. }
+            | Self::Raid { meta, .. }
+            | Self::Unraid { meta }
+            | Self::BitsBadgeTier { meta, .. }
+            | Self::Announcement { meta, .. }
+            | Self::SystemMessage { meta, .. }
+            | Self::UnknownNotice { meta, .. }
+            | Self::ClearChat { meta, .. }
+            | Self::ClearMsg { meta, .. }
+            | Self::RoomState { meta, .. }
+            | Self::UserState { meta, .. }
+            | Self::HostTarget { meta, .. }
+            | Self::Notice { meta, .. }
+            => Some(meta),
+            Self::Reconnect => None,
+        }
+    }
+
+    fn privmsg(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::Privmsg {
+            meta: EventMeta::new(msg)?,
+            user: User::sender(msg)?,
+            text: msg.text().to_owned(),
+            badges: msg.get_tag("badges").unwrap_or_default(),
+            badge_info: msg.get_tag("badge-info").unwrap_or_default(),
+            emotes: tag_parse(msg, "emotes").unwrap_or_default(),
+            reply: ReplyParent::from_message(msg),
+            bits: tag_parse(msg, "bits"),
+            reward_id: tag(msg, "custom-reward-id"),
+            highlighted: msg.get_tag("msg-id").as_deref()
+                == Some("highlighted-message"),
+            first_msg: tag_flag(msg, "first-msg"),
+            returning: tag_flag(msg, "returning-chatter"),
+        })
+    }
+
+    fn whisper(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::Whisper {
+            meta: EventMeta::new(msg)?,
+            user: User::sender(msg)?,
+            text: msg.text().to_owned(),
+            thread_id: tag(msg, "thread-id"),
+        })
+    }
+
+    fn usernotice(msg: &Message) -> Result<Self, DecodeError> {
+        let msg_id: String = tag_req(msg, "msg-id")?;
+        let meta: EventMeta = EventMeta::new(msg)?;
+        let text: Option<String> = msg.trail.clone()
+            .filter(|trail| !trail.is_empty());
+
+        let event = match msg_id.as_str() {
+            "sub" => Self::Sub {
+                meta,
+                user: User::sender(msg)?,
+                sub: SubDetails::new(msg),
+                text,
+            },
+            "resub" => Self::Resub {
+                meta,
+                user: User::sender(msg)?,
+                sub: SubDetails::new(msg),
+                text,
+            },
+            "extendsub" => Self::ExtendSub {
+                meta,
+                user: User::sender(msg)?,
+                sub: SubDetails::new(msg),
+                end_month: tag_parse(msg, "msg-param-sub-benefit-end-month"),
+                text,
+            },
+            "subgift" => Self::SubGift {
+                meta,
+                user: User::sender(msg)?,
+                recipient: User::from_params(
+                    msg,
+                    "msg-param-recipient-user-name",
+                    "msg-param-recipient-display-name",
+                    "msg-param-recipient-id",
+                )?,
+                plan: tag_parse(msg, "msg-param-sub-plan"),
+                months: tag_parse(msg, "msg-param-months"),
+                cumulative_months: tag_parse(msg, "msg-param-cumulative-months"),
+                sender_count: tag_parse(msg, "msg-param-sender-count"),
+                origin_id: tag(msg, "msg-param-origin-id"),
+            },
+            "submysterygift" => Self::MysteryGift {
+                meta,
+                user: User::sender(msg)?,
+                count: tag_parse_req(msg, "msg-param-mass-gift-count")?,
+                plan: tag_parse(msg, "msg-param-sub-plan"),
+                sender_count: tag_parse(msg, "msg-param-sender-count"),
+                origin_id: tag(msg, "msg-param-origin-id"),
+            },
+            "standardpayforward" => Self::PayForward {
+                meta,
+                user: User::sender(msg)?,
+                prior_gifter: tag(msg, "msg-param-prior-gifter-user-name"),
+                recipient: Some(tag_req(msg, "msg-param-recipient-user-name")?),
+            },
+            "communitypayforward" => Self::PayForward {
+                meta,
+                user: User::sender(msg)?,
+                prior_gifter: tag(msg, "msg-param-prior-gifter-user-name"),
+                recipient: None,
+            },
+            "giftpaidupgrade" => Self::SubUpgrade {
+                meta,
+                user: User::sender(msg)?,
+                from: Upgrade::Gift {
+                    sender: tag_req(msg, "msg-param-sender-login")?,
+                },
+            },
+            "anongiftpaidupgrade" => Self::SubUpgrade {
+                meta,
+                user: User::sender(msg)?,
+                from: Upgrade::AnonGift,
+            },
+            "primepaidupgrade" => Self::SubUpgrade {
+                meta,
+                user: User::sender(msg)?,
+                from: Upgrade::Prime,
+            },
+            "raid" => Self::Raid {
+                meta,
+                user: User::sender(msg)?,
+                from: tag_req(msg, "msg-param-displayName")?,
+                viewers: tag_parse_req(msg, "msg-param-viewerCount")?,
+            },
+            "unraid" => Self::Unraid { meta },
+            "bitsbadgetier" => Self::BitsBadgeTier {
+                meta,
+                user: User::sender(msg)?,
+                threshold: tag_parse(msg, "msg-param-threshold"),
+                text,
+            },
+            "announcement" => Self::Announcement {
+                meta,
+                user: User::sender(msg)?,
+                color: tag(msg, "msg-param-color"),
+                text: msg.text().to_owned(),
+            },
+            "charity" | "rewardgift" | "ritual" => Self::SystemMessage {
+                meta,
+                text: tag_req(msg, "system-msg")?,
+                msg_id,
+            },
+            _ => Self::UnknownNotice {
+                meta,
+                system_msg: tag(msg, "system-msg"),
+                msg_id,
+            },
+        };
+
+        Ok(event)
+    }
+
+    fn clearchat(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::ClearChat {
+            meta: EventMeta::new(msg)?,
+            target: msg.trail.clone().filter(|trail| !trail.is_empty()),
+            target_id: tag_parse(msg, "target-user-id"),
+            duration: tag_parse(msg, "ban-duration"),
+            reason: tag(msg, "ban-reason"),
+        })
+    }
+
+    fn clearmsg(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::ClearMsg {
+            meta: EventMeta::new(msg)?,
+            login: tag_req(msg, "login")?,
+            target_msg_id: tag(msg, "target-msg-id"),
+            text: msg.text().to_owned(),
+        })
+    }
+
+    fn roomstate(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::RoomState {
+            meta: EventMeta::new(msg)?,
+            emote_only: msg.get_tag("emote-only").map(|v| v != "0"),
+            followers_only: tag_parse(msg, "followers-only"),
+            unique: msg.get_tag("r9k").map(|v| v != "0"),
+            slow: tag_parse(msg, "slow"),
+            subs_only: msg.get_tag("subs-only").map(|v| v != "0"),
+        })
+    }
+
+    fn userstate(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::UserState {
+            meta: EventMeta::new(msg)?,
+            badges: msg.get_tag("badges").unwrap_or_default(),
+            badge_info: msg.get_tag("badge-info").unwrap_or_default(),
+            display_name: tag(msg, "display-name"),
+        })
+    }
+
+    fn hosttarget(msg: &Message) -> Result<Self, DecodeError> {
+        let (target, viewers) = crate::irc::split_at_char(msg.text(), ' ');
+
+        Ok(Self::HostTarget {
+            meta: EventMeta::new(msg)?,
+            target: match target {
+                "" | "-" => None,
+                name => Some(name.to_owned()),
+            },
+            viewers: viewers.parse().ok(),
+        })
+    }
+
+    fn notice(msg: &Message) -> Result<Self, DecodeError> {
+        Ok(Self::Notice {
+            meta: EventMeta::new(msg)?,
+            msg_id: tag_parse(msg, "msg-id"),
+            text: msg.text().to_owned(),
+        })
+    }
+}
+
+impl TryFrom<&Message> for TwitchEvent {
+    type Error = DecodeError;
+
+    fn try_from(msg: &Message) -> Result<Self, Self::Error> {
+        match msg.command.as_str() {
+            "PRIVMSG" => Self::privmsg(msg),
+            "WHISPER" => Self::whisper(msg),
+            "USERNOTICE" => Self::usernotice(msg),
+            "CLEARCHAT" => Self::clearchat(msg),
+            "CLEARMSG" => Self::clearmsg(msg),
+            "ROOMSTATE" => Self::roomstate(msg),
+            "USERSTATE" => Self::userstate(msg),
+            "HOSTTARGET" => Self::hosttarget(msg),
+            "RECONNECT" => Ok(Self::Reconnect),
+            "NOTICE" => Self::notice(msg),
+            _ => Err(DecodeError::UnknownCommand),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(line: &str) -> TwitchEvent {
+        let msg: Message = line.parse().expect("Failed to parse sample.");
+        TwitchEvent::try_from(&msg).expect("Failed to decode sample.")
+    }
+
+    #[test]
+    fn test_decode_resub() {
+        let event = decode(r"@badge-info=subscriber/8;badges=subscriber/6;display-name=AsdfQwert;id=db25007f-7a18-43eb-9379-80131e44d633;login=asdfqwert;msg-id=resub;msg-param-cumulative-months=8;msg-param-should-share-streak=1;msg-param-streak-months=2;msg-param-sub-plan=1000;room-id=1337;system-msg=asdfqwert\ssubscribed\sat\sTier\s1.;tmi-sent-ts=1507246572675;user-id=123456789 :tmi.twitch.tv USERNOTICE #zxcv :Great stream");
+
+        match event {
+            TwitchEvent::Resub { meta, user, sub, text } => {
+                assert_eq!(meta.channel, "#zxcv");
+                assert_eq!(meta.room_id, Some(1337));
+                assert_eq!(
+                    meta.sent.map(|dt| dt.timestamp_millis()),
+                    Some(1507246572675),
+                );
+                assert_eq!(user.login, "asdfqwert");
+                assert_eq!(user.display_name.as_deref(), Some("AsdfQwert"));
+                assert_eq!(user.id, Some(123456789));
+                assert_eq!(sub.plan, Some(SubPlan::Tier1));
+                assert_eq!(sub.streak_months, Some(2));
+                assert_eq!(sub.cumulative_months, Some(8));
+                assert_eq!(text.as_deref(), Some("Great stream"));
+            }
+            other => panic!("Decoded into wrong event: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_decode_gifts() {
+        let event = decode("@login=gifter;msg-id=submysterygift;msg-param-mass-gift-count=5;msg-param-origin-id=ab\\scd;msg-param-sender-count=50;msg-param-sub-plan=2000 :tmi.twitch.tv USERNOTICE #zxcv");
+
+        assert_eq!(event, TwitchEvent::MysteryGift {
+            meta: EventMeta { channel: "#zxcv".into(), ..Default::default() },
+            user: User { login: "gifter".into(), ..Default::default() },
+            count: 5,
+            plan: Some(SubPlan::Tier2),
+            sender_count: Some(50),
+            origin_id: Some("ab cd".into()),
+        });
+
+        let msg: Message = "@login=gifter;msg-id=subgift;msg-param-months=2 :tmi.twitch.tv USERNOTICE #zxcv"
+            .parse().expect("Failed to parse sample.");
+
+        assert_eq!(
+            TwitchEvent::try_from(&msg),
+            Err(DecodeError::MissingTag("msg-param-recipient-user-name")),
+        );
+    }
+
+    #[test]
+    fn test_decode_other() {
+        assert_eq!(
+            decode("@ban-duration=600;target-user-id=8675309 :tmi.twitch.tv CLEARCHAT #zxcv :somejerk"),
+            TwitchEvent::ClearChat {
+                meta: EventMeta { channel: "#zxcv".into(), ..Default::default() },
+                target: Some("somejerk".into()),
+                target_id: Some(8675309),
+                duration: Some(600),
+                reason: None,
+            },
+        );
+        assert_eq!(
+            decode("@emote-only=0;slow=30 :tmi.twitch.tv ROOMSTATE #zxcv"),
+            TwitchEvent::RoomState {
+                meta: EventMeta { channel: "#zxcv".into(), ..Default::default() },
+                emote_only: Some(false),
+                followers_only: None,
+                unique: None,
+                slow: Some(30),
+                subs_only: None,
+            },
+        );
+
+        assert_eq!(
+            decode("@msg-id=msg_slowmode :tmi.twitch.tv NOTICE #zxcv :This room is in slow mode."),
+            TwitchEvent::Notice {
+                meta: EventMeta { channel: "#zxcv".into(), ..Default::default() },
+                msg_id: Some(NoticeId::SlowMode),
+                text: "This room is in slow mode.".into(),
+            },
+        );
+        assert_eq!(
+            "msg_followersonly_zero".parse::<NoticeId>(),
+            Ok(NoticeId::FollowersOnly),
+        );
+        assert_eq!(
+            "msg_new_thing".parse::<NoticeId>(),
+            Ok(NoticeId::Other("msg_new_thing".into())),
+        );
+
+        match decode("@first-msg=1;returning-chatter=0 :qwert!qwert@qwert.tmi.twitch.tv PRIVMSG #zxcv :hi") {
+            TwitchEvent::Privmsg { first_msg, returning, .. } => {
+                assert!(first_msg);
+                assert!(!returning);
+            }
+            other => panic!("Decoded into wrong event: {:?}", other),
+        }
+
+        let msg: Message = ":tmi.twitch.tv JOIN #zxcv".parse().unwrap();
+        assert_eq!(
+            TwitchEvent::try_from(&msg),
+            Err(DecodeError::UnknownCommand),
+        );
+    }
+
+    #[test]
+    fn test_emotes() {
+        let emotes: Emotes = "25:0-4,12-16/1902:6-10".parse()
+            .expect("Failed to parse emotes.");
+        let words: Vec<(&str, &str)> = emotes
+            .locate("Kappa Keepo Kappa")
+            .map(|(span, _, word)| (span.id.as_str(), word))
+            .collect();
+
+        assert_eq!(words, [("25", "Kappa"), ("1902", "Keepo"), ("25", "Kappa")]);
+
+        //  Positions are counted in code points, so the multi-byte characters
+        //      before the Emote must not throw it off.
+        let emotes: Emotes = "25:4-8".parse().unwrap();
+        assert_eq!(
+            emotes.highlight("héé Kappa ✨", "\x02", "\x02"),
+            "héé \x02Kappa\x02 ✨",
+        );
+
+        //  Spans that run off the end of the text are not used.
+        assert_eq!(emotes.highlight("héé Kap", "[", "]"), "héé Kap");
+
+        assert_eq!("".parse::<Emotes>(), Ok(Emotes::default()));
+        assert_eq!("25:4".parse::<Emotes>(), Err(DecodeError::BadTag("emotes")));
+        assert_eq!("25:8-4".parse::<Emotes>(), Err(DecodeError::BadTag("emotes")));
+        assert_eq!(
+            format!("25:0-{}", usize::MAX).parse::<Emotes>(),
+            Err(DecodeError::BadTag("emotes")),
+        );
+    }
+
+    #[test]
+    fn test_reply_parent() {
+        let event = decode(r"@badges=;display-name=Qwert;emotes=;id=9a3b6c9c-8d39-4c71-9c0f-d0f1e2a3b4c5;reply-parent-display-name=AsdfQwert;reply-parent-msg-body=this\sis\sa\svery\slong\smessage\sindeed;reply-parent-msg-id=b34ccfc7-4977-403a-8a94-33c6bac34fb8;reply-parent-user-id=123456789;reply-parent-user-login=asdfqwert;room-id=1337;tmi-sent-ts=1642696567751;user-id=987654321 :qwert!qwert@qwert.tmi.twitch.tv PRIVMSG #zxcv :@AsdfQwert  it sure is");
+
+        let (parent, text) = match event {
+            TwitchEvent::Privmsg { reply: Some(parent), text, .. } => (parent, text),
+            other => panic!("Decoded into wrong event: {:?}", other),
+        };
+
+        assert_eq!(parent.msg_id, "b34ccfc7-4977-403a-8a94-33c6bac34fb8");
+        assert!(parent.is_to("ASDFQWERT"));
+        assert_eq!(parent.quote(14), "↪ @AsdfQwert: this is a very…");
+        assert_eq!(parent.quote(100), "↪ @AsdfQwert: this is a very long message indeed");
+
+        assert_eq!(parent.strip_mention(&text), "it sure is");
+        assert_eq!(parent.strip_mention("@asdfqwert"), "");
+        assert_eq!(parent.strip_mention("@asdfqwerty hi"), "@asdfqwerty hi");
+        assert_eq!(parent.strip_mention("hi @asdfqwert"), "hi @asdfqwert");
+    }
+}
@@ -0,0 +1,83 @@
+//! Golden-file tests for the output of USERNOTICE events.
+//!
+//! Each `.irc` file in `tests/fixtures/usernotice` holds raw lines captured
+//!     from Twitch. They are run through the plugin in order, and everything it
+//!     prints is compared to the `.expected` file of the same name.
+//!
+//! To write the expected files from the current output, rather than checking
+//!     against them, set `HEXTWITCH_BLESS=1`:
+//!
+//!     HEXTWITCH_BLESS=1 cargo test --features mock --test usernotice
+
+use std::{env, fs, path::{Path, PathBuf}};
+use hextwitch::host::MockHost;
+
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/usernotice");
+
+
+fn render(path: &Path) -> String {
+    let host = MockHost::new("#fixture").install();
+    //  Tab colors are remembered for the whole process, so they would depend on
+    //      which fixtures ran first. A focused tab is never colored.
+    host.focused.set(true);
+    let input: String = fs::read_to_string(path).unwrap();
+    let mut output = String::new();
+
+    for line in input.lines().filter(|line| !line.trim().is_empty()) {
+        host.replay(line);
+
+        for out in host.take_lines() {
+            output.push_str(&out);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+
+#[test]
+fn test_usernotice_golden() {
+    let bless: bool = env::var_os("HEXTWITCH_BLESS").is_some_and(|v| v != "0");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(FIXTURES).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "irc"))
+        .collect();
+    let mut failed: Vec<String> = Vec::new();
+
+    fixtures.sort();
+    assert!(!fixtures.is_empty(), "No fixtures found in {}", FIXTURES);
+
+    for path in fixtures {
+        let expected_path: PathBuf = path.with_extension("expected");
+        let actual: String = render(&path);
+
+        if bless {
+            fs::write(&expected_path, &actual).unwrap();
+            continue;
+        }
+
+        let expected: String = fs::read_to_string(&expected_path)
+            .unwrap_or_else(|_| panic!(
+                "Missing {}; run with HEXTWITCH_BLESS=1 to create it",
+                expected_path.display(),
+            ));
+
+        if actual != expected {
+            failed.push(format!(
+                "{}:\n--- expected\n{}--- actual\n{}",
+                path.file_name().unwrap().to_string_lossy(),
+                expected,
+                actual,
+            ));
+        }
+    }
+
+    assert!(
+        failed.is_empty(),
+        "Output differs from {} fixture(s):\n\n{}",
+        failed.len(),
+        failed.join("\n"),
+    );
+}